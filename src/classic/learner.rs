@@ -0,0 +1,125 @@
+use super::Body;
+use crate::quorum::QuorumSystem;
+use crate::{Address, Epoch, Header, Instant, Learning, Msg, Node, Slot, Timer, Value};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+/// A learner finding out about the chosen value either through
+/// [`Body::Learned`] notifications by a quorum of acceptors or through a
+/// [`Body::Decide`], depending on the [`Learning`] strategy.
+#[derive(Clone, Debug)]
+pub struct Learner {
+    address: Address,
+    pub acceptors: Vec<Address>,
+    /// All learners of the cluster, the first one being the distinguished
+    /// learner.
+    learners: Vec<Address>,
+    learning: Learning,
+    quorums: Box<dyn QuorumSystem>,
+    inbox: VecDeque<Msg<Body>>,
+    /// Acceptors that notified about accepting a value in an epoch.
+    votes: BTreeMap<(Epoch, Value), BTreeSet<Address>>,
+    learned: Option<Value>,
+}
+
+impl Node<Body> for Learner {
+    fn receive(&mut self, m: Msg<Body>) {
+        self.inbox.push_back(m);
+    }
+
+    fn process(&mut self, now: Instant) -> Vec<Msg<Body>> {
+        self.process(now)
+    }
+
+    fn crash(&mut self, _now: Instant) {
+        // Learners do not persist anything, thus learning from scratch.
+        self.inbox.clear();
+        self.votes.clear();
+        self.learned = None;
+    }
+
+    fn timer(&self) -> Timer {
+        Timer::Idle
+    }
+}
+
+impl crate::Learner<Body> for Learner {
+    fn learned(&self) -> BTreeMap<Slot, Value> {
+        self.learned.iter().map(|v| (Slot(0), v.clone())).collect()
+    }
+
+    fn set_quorums(&mut self, quorums: Box<dyn QuorumSystem>) {
+        self.quorums = quorums;
+    }
+
+    fn set_learners(&mut self, learners: Vec<Address>, learning: Learning) {
+        self.learners = learners;
+        self.learning = learning;
+    }
+}
+
+impl Learner {
+    pub fn new(address: Address, acceptors: Vec<Address>) -> Self {
+        Self {
+            address,
+            acceptors,
+            learners: vec![],
+            learning: Learning::AllToAll,
+            quorums: Default::default(),
+            inbox: Default::default(),
+            votes: BTreeMap::new(),
+            learned: None,
+        }
+    }
+
+    fn process(&mut self, now: Instant) -> Vec<Msg<Body>> {
+        let messages: Vec<Msg<Body>> = self.inbox.drain(0..).collect();
+        messages
+            .into_iter()
+            .flat_map(|m| self.process_msg(m, now))
+            .collect()
+    }
+
+    fn process_msg(&mut self, m: Msg<Body>, now: Instant) -> Vec<Msg<Body>> {
+        if self.learned.is_some() {
+            return vec![];
+        }
+
+        match m.body {
+            Body::Learned(epoch, value) => {
+                let voters = self.votes.entry((epoch, value.clone())).or_default();
+                // Duplicate notifications, e.g. due to a faulty network, are
+                // deduplicated by the set.
+                voters.insert(m.header.from);
+                if !self.quorums.is_phase2_quorum(&self.acceptors, voters) {
+                    return vec![];
+                }
+
+                self.learned = Some(value.clone());
+                self.votes.clear();
+
+                if self.learning != Learning::Distinguished {
+                    return vec![];
+                }
+
+                // Being the distinguished learner, telling all others.
+                self.learners
+                    .iter()
+                    .filter(|l| **l != self.address)
+                    .map(|l| Msg {
+                        header: Header {
+                            from: self.address.clone(),
+                            to: l.clone(),
+                            at: now + 1,
+                        },
+                        body: Body::Decide(value.clone()),
+                    })
+                    .collect()
+            }
+            Body::Decide(value) => {
+                self.learned = Some(value);
+                vec![]
+            }
+            _ => unimplemented!(),
+        }
+    }
+}
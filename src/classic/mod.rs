@@ -1,37 +1,67 @@
-use crate::{Value, Epoch};
+use crate::{Value, Epoch, RequestId};
 
 pub use proposer::Proposer;
 pub use acceptor::Acceptor;
+pub use learner::Learner;
 
 mod proposer;
 mod acceptor;
+mod learner;
 
 #[derive(Clone, PartialEq, Eq)]
 pub enum Body {
     /// Request by an end-user.
-    Request(Value),
-    /// Response by a proposer to an end-user.
-    Response(Value),
+    Request(RequestId, Value),
+    /// Response by a proposer to an end-user, with the decided value.
+    Response(RequestId, Value),
+    /// Rejection of a request by a proposer with a full queue, see
+    /// [`Proposer::with_queue_capacity`].
+    Busy(RequestId),
     Prepare(Epoch),
     /// Promised epoch, accepted epoch, accepted value.
     // TODO: Why not combine the two options, they never occur separately.
     Promise(Epoch, Option<(Epoch, Value)>),
     Propose(Epoch, Value),
     Accept(Epoch),
+    /// Notification by an acceptor to a learner about accepting the value in
+    /// the epoch.
+    Learned(Epoch, Value),
+    /// Notification to a learner about the value being chosen.
+    Decide(Value),
 }
 
 impl crate::Body for Body {
-    fn is_request(&self) -> Option<Value> {
-        if let Body::Request(v) = self {
-            return Some(v.clone());
+    fn new_request(id: RequestId, v: Value) -> Self {
+        Body::Request(id, v)
+    }
+
+    fn is_request(&self) -> Option<(RequestId, Value)> {
+        if let Body::Request(id, v) = self {
+            return Some((*id, v.clone()));
+        }
+
+        None
+    }
+
+    fn is_response(&self) -> Option<(RequestId, Value)> {
+        if let Body::Response(id, v) = self {
+            return Some((*id, v.clone()));
+        }
+
+        None
+    }
+
+    fn is_rejection(&self) -> Option<RequestId> {
+        if let Body::Busy(id) = self {
+            return Some(*id);
         }
 
         None
     }
 
-    fn is_response(&self) -> Option<Value> {
-        if let Body::Response(v) = self {
-            return Some(v.clone());
+    fn is_propose(&self) -> Option<(Epoch, Value)> {
+        if let Body::Propose(e, v) = self {
+            return Some((*e, v.clone()));
         }
 
         None
@@ -41,12 +71,15 @@ impl crate::Body for Body {
 impl std::fmt::Debug for Body {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Body::Request(v) => write!(f, "request({:?})", v),
-            Body::Response(v) => write!(f, "response({:?})", v),
+            Body::Request(id, v) => write!(f, "request({:?}, {:?})", id, v),
+            Body::Response(id, v) => write!(f, "response({:?}, {:?})", id, v),
+            Body::Busy(id) => write!(f, "busy({:?})", id),
             Body::Prepare(e) => write!(f, "prepare({:?})", e),
             Body::Promise(e, a) => write!(f, "promise({:?}, {:?})", e, a),
             Body::Propose(e, v) => write!(f, "propose({:?}, {:?})", e, v),
             Body::Accept(e) => write!(f, "accept({:?})", e),
+            Body::Learned(e, v) => write!(f, "learned({:?}, {:?})", e, v),
+            Body::Decide(v) => write!(f, "decide({:?})", v),
         }
     }
 }
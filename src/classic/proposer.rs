@@ -1,17 +1,32 @@
-use crate::{Address, Epoch, Header, Instant, Msg, Node, Value};
-use std::collections::VecDeque;
+use crate::quorum::QuorumSystem;
+use crate::timeout::{ProposerConfig, Timeout};
+use crate::{Address, Epoch, Header, Instant, Learning, Msg, Node, RequestId, Timer, Value};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use super::Body;
 
-const TIMEOUT: Instant = Instant(10);
-
 /// A sequential proposer, handling a single request at a time.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Proposer {
     address: Address,
     pub acceptors: Vec<Address>,
     inbox: VecDeque<Msg<Body>>,
     epoch: Epoch,
     state: ProposerState,
+    /// Requests waiting for the one being handled, oldest first.
+    queue: VecDeque<(Address, RequestId, Value)>,
+    /// Number of requests the queue holds at most, see
+    /// [`Proposer::with_queue_capacity`].
+    queue_capacity: usize,
+    /// Values decided for the requests responded to so far, answering a
+    /// resubmitted request right away.
+    answered: BTreeMap<RequestId, Value>,
+    quorums: Box<dyn QuorumSystem>,
+    /// Learners to tell about chosen values.
+    learners: Vec<Address>,
+    timeout: Timeout,
+    /// Instant the messages of the current phase were sent at, measuring the
+    /// round trip of each reply.
+    sent_at: Instant,
 }
 
 impl Node<Body> for Proposer {
@@ -22,9 +37,48 @@ impl Node<Body> for Proposer {
     fn process(&mut self, now: Instant) -> Vec<Msg<Body>> {
         self.process(now)
     }
+
+    fn crash(&mut self, _now: Instant) {
+        self.inbox.clear();
+        self.queue.clear();
+        self.state = ProposerState::Idle;
+        // Round trips observed before the crash are forgotten.
+        self.timeout.restart();
+        // A request resubmitted after the crash goes through another round,
+        // deciding the same value anyway.
+        self.answered.clear();
+        // The epoch is persisted. Moving on to the next one prevents reusing
+        // an epoch for a different value after the crash.
+        self.epoch = Epoch::new(self.epoch.epoch + 1, self.epoch.identifier);
+    }
+
+    fn timer(&self) -> Timer {
+        match self.state.last_progress_at() {
+            Some(t) => Timer::At(t + self.timeout.get()),
+            None => Timer::Idle,
+        }
+    }
 }
 
-impl crate::Proposer<Body> for Proposer{}
+impl crate::Proposer<Body> for Proposer {
+    fn set_quorums(&mut self, quorums: Box<dyn QuorumSystem>) {
+        self.quorums = quorums;
+    }
+
+    fn set_learners(&mut self, learners: Vec<Address>, learning: Learning) {
+        if learning == Learning::Proposer {
+            self.learners = learners;
+        }
+    }
+
+    fn set_config(&mut self, config: ProposerConfig) {
+        self.timeout.set_config(config);
+    }
+
+    fn config(&self) -> ProposerConfig {
+        self.timeout.config()
+    }
+}
 
 impl Proposer {
     pub fn new(address: Address, initial_epoch: Epoch, acceptors: Vec<Address>) -> Self {
@@ -34,15 +88,35 @@ impl Proposer {
             inbox: Default::default(),
             epoch: initial_epoch,
             state: ProposerState::Idle,
+            queue: VecDeque::new(),
+            queue_capacity: usize::MAX,
+            answered: BTreeMap::new(),
+            quorums: Default::default(),
+            learners: vec![],
+            timeout: Timeout::new(ProposerConfig::default(), initial_epoch.identifier as u64),
+            sent_at: Instant::default(),
         }
     }
 
+    /// Queues at most the given number of requests while handling another
+    /// one, rejecting further requests with [`Body::Busy`]. Unbounded by
+    /// default.
+    pub fn with_queue_capacity(mut self, capacity: usize) -> Self {
+        self.queue_capacity = capacity;
+        self
+    }
+
+    /// Uses the given timeouts instead of the default fixed one.
+    pub fn with_config(mut self, config: ProposerConfig) -> Self {
+        self.timeout.set_config(config);
+        self
+    }
+
     fn process(&mut self, now: Instant) -> Vec<Msg<Body>>{
         let messages: Vec<Msg<Body>> = self.inbox.drain(0..).collect();
         let responses: Vec<Msg<Body>> = messages
             .into_iter()
-            .map(|m| self.process_msg(m, now))
-            .flatten()
+            .flat_map(|m| self.process_msg(m, now))
             .collect();
         if !responses.is_empty() {
             // We made progress, thus returning.
@@ -53,23 +127,26 @@ impl Proposer {
         if self
             .state
             .last_progress_at()
-            .map(|t| now - t < TIMEOUT)
+            .map(|t| !self.timeout.is_expired(t, now))
             .unwrap_or(true)
         {
             return vec![];
         }
 
         // We timed out - going back to preparing.
+        self.timeout.expire();
 
         self.epoch = Epoch::new(self.epoch.epoch + 1, self.epoch.identifier);
 
-        let value = self
+        let (client, id, value) = self
             .state
-            .value()
-            .expect("can't be reached from idle state, thus there is a value");
+            .request()
+            .expect("can't be reached from idle state, thus there is a request");
 
         self.state = ProposerState::Preparing {
             last_progress_at: now,
+            client,
+            id,
             value,
             promises: vec![],
         };
@@ -81,28 +158,54 @@ impl Proposer {
 
     fn process_msg(&mut self, m: Msg<Body>, now: Instant) -> Vec<Msg<Body>> {
         match m.body {
-            Body::Request(v) => self.process_request(m.header, v, now),
+            Body::Request(id, v) => self.process_request(m.header.from, id, v, now),
             Body::Promise(promised_epoch, accepted) => {
-                self.process_promise(promised_epoch, accepted, now)
+                self.process_promise(m.header.from, promised_epoch, accepted, now)
             }
-            Body::Accept(epoch) => self.process_accept(epoch, now),
-            Body::Prepare(_) | Body::Propose(_, _) | Body::Response(_) => unimplemented!(),
+            Body::Accept(epoch) => self.process_accept(m.header.from, epoch, now),
+            // Duplicated or misrouted messages meant for end-users and learners.
+            Body::Response(_, _) | Body::Busy(_) | Body::Learned(_, _) | Body::Decide(_) => vec![],
+            Body::Prepare(_) | Body::Propose(_, _) => unimplemented!(),
         }
     }
 
-    fn process_request(&mut self, header: Header, value: Value, now: Instant) -> Vec<Msg<Body>> {
+    fn process_request(
+        &mut self,
+        client: Address,
+        id: RequestId,
+        value: Value,
+        now: Instant,
+    ) -> Vec<Msg<Body>> {
+        // A resubmitted request is answered right away once decided, and
+        // dropped while still being handled or queued.
+        if let Some(decided) = self.answered.get(&id) {
+            return vec![self.respond(client, id, decided.clone(), now)];
+        }
+        if self
+            .state
+            .request()
+            .map(|(_, i, _)| i == id)
+            .unwrap_or(false)
+            || self.queue.iter().any(|(_, i, _)| *i == id)
+        {
+            return vec![];
+        }
+
         match self.state {
             ProposerState::Unreachable => unreachable!(),
             ProposerState::Preparing { .. } | ProposerState::Proposing { .. } => {
-                self.inbox.push_back(Msg {
-                    header,
-                    body: Body::Request(value),
-                });
-                return vec![];
+                if self.queue.len() >= self.queue_capacity {
+                    return vec![self.send(client, Body::Busy(id), now)];
+                }
+
+                self.queue.push_back((client, id, value));
+                vec![]
             }
             ProposerState::Idle => {
                 self.state = ProposerState::Preparing {
                     last_progress_at: now,
+                    client,
+                    id,
                     value,
                     promises: vec![],
                 };
@@ -116,6 +219,7 @@ impl Proposer {
 
     fn process_promise(
         &mut self,
+        acceptor: Address,
         promised_epoch: Epoch,
         accepted: Option<(Epoch, Value)>,
         now: Instant,
@@ -130,21 +234,37 @@ impl Proposer {
             ProposerState::Unreachable => unreachable!(),
             ProposerState::Idle | ProposerState::Proposing { .. } => {
                 self.state = state;
-                return vec![];
+                vec![]
             }
             ProposerState::Preparing {
+                client,
+                id,
                 value,
                 mut promises,
                 ..
             } => {
-                promises.push(Promise {
-                    epoch: promised_epoch,
-                    accepted,
-                });
+                // Ignore duplicate promises, e.g. due to a faulty network.
+                if promises.iter().any(|p| p.acceptor == acceptor) {
+                    self.state = ProposerState::Preparing {
+                        last_progress_at: now,
+                        client,
+                        id,
+                        value,
+                        promises,
+                    };
+                    return vec![];
+                }
+
+                self.timeout.observe(now - self.sent_at);
+                promises.push(Promise { acceptor, accepted });
 
-                if promises.len() < self.acceptors.len() / 2 + 1 {
+                let promised: BTreeSet<Address> =
+                    promises.iter().map(|p| p.acceptor.clone()).collect();
+                if !self.quorums.is_phase1_quorum(&self.acceptors, &promised) {
                     self.state = ProposerState::Preparing {
                         last_progress_at: now,
+                        client,
+                        id,
                         value,
                         promises,
                     };
@@ -168,20 +288,23 @@ impl Proposer {
                             None => accepted,
                         });
 
+                let value = highest_accepted.map(|a| a.1).unwrap_or(value);
                 self.state = ProposerState::Proposing {
                     last_progress_at: now,
-                    value: highest_accepted.map(|a| a.1).unwrap_or(value),
-                    received_accepts: 0,
+                    client,
+                    id,
+                    value: value.clone(),
+                    received_accepts: BTreeSet::new(),
                 };
 
-                let propose_body = Body::Propose(self.epoch, self.state.value().unwrap());
+                let propose_body = Body::Propose(self.epoch, value);
 
                 self.broadcast_to_acceptors(propose_body, now)
             }
         }
     }
 
-    fn process_accept(&mut self, epoch: Epoch, now: Instant) -> Vec<Msg<Body>> {
+    fn process_accept(&mut self, acceptor: Address, epoch: Epoch, now: Instant) -> Vec<Msg<Body>> {
         // Ignore any messages outside our current epoch.
         if epoch != self.epoch {
             return vec![];
@@ -192,17 +315,28 @@ impl Proposer {
             ProposerState::Unreachable => unimplemented!(),
             ProposerState::Idle | ProposerState::Preparing { .. } => {
                 self.state = state;
-                return vec![];
+                vec![]
             }
             ProposerState::Proposing {
+                client,
+                id,
                 value,
-                received_accepts,
+                mut received_accepts,
                 ..
             } => {
-                let received_accepts = received_accepts + 1;
+                // Duplicate accepts, e.g. due to a faulty network, are
+                // deduplicated by the set.
+                if received_accepts.insert(acceptor) {
+                    self.timeout.observe(now - self.sent_at);
+                }
 
-                if received_accepts < self.acceptors.len() / 2 + 1 {
+                if !self
+                    .quorums
+                    .is_phase2_quorum(&self.acceptors, &received_accepts)
+                {
                     self.state = ProposerState::Proposing {
+                        client,
+                        id,
                         value,
                         received_accepts,
                         last_progress_at: now,
@@ -212,21 +346,56 @@ impl Proposer {
 
                 self.epoch = Epoch::new(self.epoch.epoch + 1, self.epoch.identifier);
                 self.state = ProposerState::Idle;
+                self.answered.insert(id, value.clone());
 
-                return vec![Msg {
+                let decisions = self.learners.iter().map(|l| Msg {
                     header: Header {
                         from: self.address.clone(),
-                        // TODO: We need to track the client address along the way.
-                        to: Address::new(""),
+                        to: l.clone(),
                         at: now + 1,
                     },
-                    body: Body::Response(value),
-                }];
+                    body: Body::Decide(value.clone()),
+                });
+
+                let response = self.respond(client, id, value.clone(), now);
+                let mut msgs: Vec<Msg<Body>> = std::iter::once(response).chain(decisions).collect();
+
+                msgs.append(&mut self.dequeue(now));
+                msgs
             }
         }
     }
 
+    /// Starts handling the oldest queued request, if idle.
+    fn dequeue(&mut self, now: Instant) -> Vec<Msg<Body>> {
+        let mut msgs = vec![];
+        while matches!(self.state, ProposerState::Idle) {
+            let (client, id, value) = match self.queue.pop_front() {
+                Some(r) => r,
+                None => break,
+            };
+            msgs.append(&mut self.process_request(client, id, value, now));
+        }
+        msgs
+    }
+
+    fn respond(&self, client: Address, id: RequestId, value: Value, now: Instant) -> Msg<Body> {
+        self.send(client, Body::Response(id, value), now)
+    }
+
+    fn send(&self, to: Address, body: Body, now: Instant) -> Msg<Body> {
+        Msg {
+            header: Header {
+                from: self.address.clone(),
+                to,
+                at: now + 1,
+            },
+            body,
+        }
+    }
+
     fn broadcast_to_acceptors(&mut self, b: Body, now: Instant) -> Vec<Msg<Body>> {
+        self.sent_at = now;
         self.acceptors
             .iter()
             .map(|a| Msg {
@@ -244,16 +413,21 @@ impl Proposer {
 #[derive(Clone, Debug)]
 enum ProposerState {
     Idle,
-    /// Epoch and value to propose and promises received so far.
+    /// Client and value to propose on behalf of and promises received so
+    /// far.
     Preparing {
         last_progress_at: Instant,
+        client: Address,
+        id: RequestId,
         value: Value,
         promises: Vec<Promise>,
     },
     Proposing {
         last_progress_at: Instant,
+        client: Address,
+        id: RequestId,
         value: Value,
-        received_accepts: usize,
+        received_accepts: BTreeSet<Address>,
     },
     Unreachable,
 }
@@ -272,18 +446,24 @@ impl ProposerState {
         }
     }
 
-    fn value(&self) -> Option<Value> {
+    /// Returns the client, the identifier and the value of the request being
+    /// handled.
+    fn request(&self) -> Option<(Address, RequestId, Value)> {
         match self {
             ProposerState::Unreachable => unreachable!(),
             ProposerState::Idle => None,
-            ProposerState::Preparing { value, .. } => Some(value.clone()),
-            ProposerState::Proposing { value, .. } => Some(value.clone()),
+            ProposerState::Preparing {
+                client, id, value, ..
+            }
+            | ProposerState::Proposing {
+                client, id, value, ..
+            } => Some((client.clone(), *id, value.clone())),
         }
     }
 }
 
 #[derive(Clone, Debug)]
 struct Promise {
-    epoch: Epoch,
+    acceptor: Address,
     accepted: Option<(Epoch, Value)>,
 }
@@ -0,0 +1,196 @@
+use super::{Body, FAST_EPOCH};
+use crate::storage::{AcceptorState, MemoryStorage, Storage};
+use crate::{Address, Header, Instant, Msg, Node, Slot, Timer};
+use std::collections::{BTreeMap, VecDeque};
+
+/// An acceptor taking part in both the fast and the classic rounds.
+#[derive(Clone, Debug)]
+pub struct Acceptor {
+    address: Address,
+    state: AcceptorState,
+    storage: Box<dyn Storage>,
+    inbox: VecDeque<Msg<Body>>,
+    /// Replies waiting for the state they depend on to be durable.
+    outbox: Vec<(Instant, Msg<Body>)>,
+}
+
+impl Node<Body> for Acceptor {
+    fn receive(&mut self, m: Msg<Body>) {
+        self.inbox.push_back(m);
+    }
+
+    fn process(&mut self, now: Instant) -> Vec<Msg<Body>> {
+        self.process(now)
+    }
+
+    fn crash(&mut self, now: Instant) {
+        self.inbox.clear();
+        self.outbox.clear();
+        self.storage.crash(now);
+        self.state = self.storage.read().expect("to read persisted state");
+    }
+
+    fn timer(&self) -> Timer {
+        // Replies held back until the state they depend on is durable.
+        match self.outbox.iter().map(|(durable_at, _)| *durable_at).min() {
+            Some(at) => Timer::At(at),
+            None => Timer::Idle,
+        }
+    }
+}
+
+impl crate::Acceptor<Body> for Acceptor {
+    fn states(&self) -> BTreeMap<Slot, AcceptorState> {
+        std::iter::once((Slot(0), self.state.clone())).collect()
+    }
+
+    fn amnesia(&mut self, now: Instant) {
+        self.crash(now);
+        self.storage.wipe().expect("to wipe persisted state");
+        self.state = AcceptorState::default();
+    }
+}
+
+impl Acceptor {
+    pub fn new(address: Address) -> Self {
+        Acceptor::with_storage(address, Box::new(MemoryStorage::default()))
+    }
+
+    pub fn with_storage(address: Address, storage: Box<dyn Storage>) -> Self {
+        Acceptor {
+            address,
+            state: storage.read().expect("to read persisted state"),
+            storage,
+            inbox: VecDeque::new(),
+            outbox: vec![],
+        }
+    }
+
+    pub fn address(&self) -> Address {
+        self.address.clone()
+    }
+
+    pub fn process(&mut self, now: Instant) -> Vec<Msg<Body>> {
+        let (durable, outbox): (Vec<_>, Vec<_>) = self
+            .outbox
+            .drain(0..)
+            .partition(|(durable_at, _)| *durable_at <= now);
+        self.outbox = outbox;
+
+        let messages: Vec<Msg<Body>> = self.inbox.drain(0..).collect();
+        durable
+            .into_iter()
+            .map(|(_, mut m)| {
+                m.header.at = now + 1;
+                m
+            })
+            .chain(messages.into_iter().flat_map(|m| self.process_msg(m, now)))
+            .collect()
+    }
+
+    fn process_msg(&mut self, m: Msg<Body>, now: Instant) -> Vec<Msg<Body>> {
+        match m.body {
+            Body::Prepare(i) => {
+                if self.state.promised_epoch.map(|e| e > i).unwrap_or(false) {
+                    return vec![];
+                }
+
+                let mut state = self.state.clone();
+                state.promised_epoch = Some(i);
+                let body = Body::Promise(i, state.accepted.clone());
+
+                self.persist_and_reply(state, m.header.from, body, now)
+            }
+            Body::FastPropose(coordinator, value) => {
+                // Any promise is for a classic round, thus the fast round is
+                // over.
+                if self.state.promised_epoch.is_some() {
+                    return vec![];
+                }
+
+                // Only the first value of the fast round is accepted. Telling
+                // the coordinator about it lets it detect a collision, though
+                // only once the value is durable.
+                if let Some((FAST_EPOCH, accepted)) = &self.state.accepted {
+                    let reply = Msg {
+                        header: Header {
+                            from: self.address.clone(),
+                            to: coordinator,
+                            at: now + 1,
+                        },
+                        body: Body::Accept(FAST_EPOCH, accepted.clone()),
+                    };
+
+                    return match self.outbox.iter().map(|(at, _)| *at).max() {
+                        Some(durable_at) => {
+                            self.outbox.push((durable_at, reply));
+                            vec![]
+                        }
+                        None => vec![reply],
+                    };
+                }
+
+                let mut state = self.state.clone();
+                state.accepted = Some((FAST_EPOCH, value.clone()));
+
+                self.persist_and_reply(state, coordinator, Body::Accept(FAST_EPOCH, value), now)
+            }
+            Body::Propose(proposed_epoch, value) => {
+                if self
+                    .state
+                    .promised_epoch
+                    .map(|e| e > proposed_epoch)
+                    .unwrap_or(false)
+                {
+                    return vec![];
+                }
+
+                // Accepting implies promising, otherwise a proposal of a
+                // lower epoch could still replace the accepted value.
+                let mut state = self.state.clone();
+                state.promised_epoch = Some(proposed_epoch);
+                state.accepted = Some((proposed_epoch, value.clone()));
+
+                self.persist_and_reply(
+                    state,
+                    m.header.from,
+                    Body::Accept(proposed_epoch, value),
+                    now,
+                )
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    /// Persist the given state and reply to the proposer once it is durable.
+    /// Failing to persist the state results in no reply at all.
+    fn persist_and_reply(
+        &mut self,
+        state: AcceptorState,
+        to: Address,
+        body: Body,
+        now: Instant,
+    ) -> Vec<Msg<Body>> {
+        let durable_at = match self.storage.write(&state, now) {
+            Ok(durable_at) => durable_at,
+            Err(_) => return vec![],
+        };
+        self.state = state;
+
+        let reply = Msg {
+            header: Header {
+                from: self.address.clone(),
+                to,
+                at: now + 1,
+            },
+            body,
+        };
+
+        if durable_at <= now {
+            return vec![reply];
+        }
+
+        self.outbox.push((durable_at, reply));
+        vec![]
+    }
+}
@@ -0,0 +1,154 @@
+//! Fast Paxos, choosing a value within a single round trip in the absence of
+//! contention.
+//!
+//! All proposers share a single fast round, [`FAST_EPOCH`], which needs no
+//! phase 1. Along with its request to a proposer, the end-user sends the
+//! requested value straight to the acceptors, see [`Body::FastPropose`].
+//! Each acceptor accepts the first value it receives and reports it to the
+//! proposer coordinating the request. A value is chosen once a fast quorum,
+//! larger than a majority, accepted it. Concurrent requests can collide,
+//! splitting the acceptors between values. The coordinators then recover
+//! through a classic round, proposing a value that might have been chosen in
+//! the fast round, if any.
+
+use crate::quorum::QuorumSystem;
+use crate::{Address, Epoch, Header, Msg, RequestId, Value};
+use std::collections::BTreeSet;
+
+pub use acceptor::Acceptor;
+pub use proposer::Proposer;
+
+mod acceptor;
+mod proposer;
+
+/// The single fast round shared by all proposers. Classic rounds use higher
+/// epochs.
+pub const FAST_EPOCH: Epoch = Epoch {
+    epoch: 0,
+    identifier: 0,
+};
+
+/// Returns the number of acceptors needed to choose a value in a classic
+/// round, i.e. a majority.
+fn classic_quorum(acceptors: usize) -> usize {
+    acceptors / 2 + 1
+}
+
+/// Returns the number of acceptors needed to choose a value in the fast
+/// round. Any two fast quorums and a classic quorum intersect, thus at most a
+/// single value accepted by a classic quorum might have been chosen in the
+/// fast round. Without any acceptors, no value can be chosen.
+fn fast_quorum(acceptors: usize) -> usize {
+    (2 * acceptors).saturating_sub(classic_quorum(acceptors)) / 2 + 1
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub enum Body {
+    /// Request by an end-user.
+    Request(RequestId, Value),
+    /// Response by a proposer to an end-user, with the decided value.
+    Response(RequestId, Value),
+    /// Rejection of a request by a proposer with a full queue, see
+    /// [`Proposer::with_queue_capacity`].
+    Busy(RequestId),
+    Prepare(Epoch),
+    /// Promised epoch, accepted epoch, accepted value.
+    Promise(Epoch, Option<(Epoch, Value)>),
+    /// Proposal of the fast round by an end-user, along with the proposer
+    /// coordinating the request.
+    FastPropose(Address, Value),
+    /// Proposal of a classic round.
+    Propose(Epoch, Value),
+    /// Accepted epoch and value. In the fast round an acceptor replies with
+    /// the value it accepted, which might differ from the proposed one.
+    Accept(Epoch, Value),
+}
+
+impl crate::Body for Body {
+    fn new_request(id: RequestId, v: Value) -> Self {
+        Body::Request(id, v)
+    }
+
+    fn is_request(&self) -> Option<(RequestId, Value)> {
+        if let Body::Request(id, v) = self {
+            return Some((*id, v.clone()));
+        }
+
+        None
+    }
+
+    fn is_response(&self) -> Option<(RequestId, Value)> {
+        if let Body::Response(id, v) = self {
+            return Some((*id, v.clone()));
+        }
+
+        None
+    }
+
+    fn is_rejection(&self) -> Option<RequestId> {
+        if let Body::Busy(id) = self {
+            return Some(*id);
+        }
+
+        None
+    }
+
+    /// Proposes the requested value to the acceptors in the fast round.
+    fn submit(request: Msg<Self>, acceptors: &[Address]) -> Vec<Msg<Self>> {
+        let value = match &request.body {
+            Body::Request(_, v) => v.clone(),
+            _ => return vec![request],
+        };
+
+        let mut msgs: Vec<Msg<Self>> = acceptors
+            .iter()
+            .map(|a| Msg {
+                header: Header {
+                    from: request.header.from.clone(),
+                    to: a.clone(),
+                    at: request.header.at,
+                },
+                body: Body::FastPropose(request.header.to.clone(), value.clone()),
+            })
+            .collect();
+        msgs.push(request);
+        msgs
+    }
+
+    fn is_propose(&self) -> Option<(Epoch, Value)> {
+        match self {
+            Body::FastPropose(_, v) => Some((FAST_EPOCH, v.clone())),
+            Body::Propose(e, v) => Some((*e, v.clone())),
+            _ => None,
+        }
+    }
+
+    /// The quorum sizes are fixed, thus ignoring the given quorum system.
+    fn is_chosen(
+        epoch: Epoch,
+        acceptors: &[Address],
+        accepted: &BTreeSet<Address>,
+        _quorums: &dyn QuorumSystem,
+    ) -> bool {
+        if epoch == FAST_EPOCH {
+            accepted.len() >= fast_quorum(acceptors.len())
+        } else {
+            accepted.len() >= classic_quorum(acceptors.len())
+        }
+    }
+}
+
+impl std::fmt::Debug for Body {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Body::Request(id, v) => write!(f, "request({:?}, {:?})", id, v),
+            Body::Response(id, v) => write!(f, "response({:?}, {:?})", id, v),
+            Body::Busy(id) => write!(f, "busy({:?})", id),
+            Body::Prepare(e) => write!(f, "prepare({:?})", e),
+            Body::Promise(e, a) => write!(f, "promise({:?}, {:?})", e, a),
+            Body::FastPropose(c, v) => write!(f, "fast propose({:?}, {:?})", c, v),
+            Body::Propose(e, v) => write!(f, "propose({:?}, {:?})", e, v),
+            Body::Accept(e, v) => write!(f, "accept({:?}, {:?})", e, v),
+        }
+    }
+}
@@ -0,0 +1,504 @@
+use super::{classic_quorum, fast_quorum, Body, FAST_EPOCH};
+use crate::timeout::{ProposerConfig, Timeout};
+use crate::{Address, Epoch, Header, Instant, Msg, Node, RequestId, Timer, Value};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+/// A sequential proposer, handling a single request at a time. It coordinates
+/// the fast round the end-user proposed the request in, recovering through a
+/// classic round on a collision or a timeout.
+#[derive(Clone, Debug)]
+pub struct Proposer {
+    address: Address,
+    pub acceptors: Vec<Address>,
+    inbox: VecDeque<Msg<Body>>,
+    epoch: Epoch,
+    state: ProposerState,
+    /// Requests waiting for the one being handled, oldest first.
+    queue: VecDeque<(Address, RequestId, Value)>,
+    /// Number of requests the queue holds at most, see
+    /// [`Proposer::with_queue_capacity`].
+    queue_capacity: usize,
+    /// Values decided for the requests responded to so far, answering a
+    /// resubmitted request right away.
+    answered: BTreeMap<RequestId, Value>,
+    /// Whether this proposer started a classic round, thus ending the fast
+    /// round. Further requests go straight to a classic round.
+    fast_round_over: bool,
+    /// Value each acceptor reported to have accepted in the fast round,
+    /// possibly before the request it was proposed along with arrived.
+    fast_accepts: BTreeMap<Address, Value>,
+    timeout: Timeout,
+    /// Instant the messages of the current round or phase were sent at,
+    /// measuring the round trip of each reply.
+    sent_at: Instant,
+}
+
+impl Node<Body> for Proposer {
+    fn receive(&mut self, m: Msg<Body>) {
+        self.inbox.push_back(m);
+    }
+
+    fn process(&mut self, now: Instant) -> Vec<Msg<Body>> {
+        self.process(now)
+    }
+
+    fn crash(&mut self, _now: Instant) {
+        self.inbox.clear();
+        self.queue.clear();
+        self.state = ProposerState::Idle;
+        // A request resubmitted after the crash goes through another round,
+        // deciding the same value anyway.
+        self.answered.clear();
+        self.fast_round_over = false;
+        self.fast_accepts.clear();
+        // Round trips observed before the crash are forgotten.
+        self.timeout.restart();
+        // The epoch is persisted. Moving on to the next one prevents reusing
+        // an epoch for a different value after the crash.
+        self.epoch = Epoch::new(self.epoch.epoch + 1, self.epoch.identifier);
+    }
+
+    fn timer(&self) -> Timer {
+        match self.state.last_progress_at() {
+            Some(t) => Timer::At(t + self.timeout.get()),
+            None => Timer::Idle,
+        }
+    }
+}
+
+impl crate::Proposer<Body> for Proposer {
+    fn set_config(&mut self, config: ProposerConfig) {
+        self.timeout.set_config(config);
+    }
+
+    fn config(&self) -> ProposerConfig {
+        self.timeout.config()
+    }
+}
+
+impl Proposer {
+    pub fn new(address: Address, initial_epoch: Epoch, acceptors: Vec<Address>) -> Self {
+        Self {
+            address,
+            acceptors,
+            inbox: Default::default(),
+            epoch: initial_epoch,
+            state: ProposerState::Idle,
+            queue: VecDeque::new(),
+            queue_capacity: usize::MAX,
+            answered: BTreeMap::new(),
+            fast_round_over: false,
+            fast_accepts: BTreeMap::new(),
+            timeout: Timeout::new(ProposerConfig::default(), initial_epoch.identifier as u64),
+            sent_at: Instant::default(),
+        }
+    }
+
+    /// Queues at most the given number of requests while handling another
+    /// one, rejecting further requests with [`Body::Busy`]. Unbounded by
+    /// default.
+    pub fn with_queue_capacity(mut self, capacity: usize) -> Self {
+        self.queue_capacity = capacity;
+        self
+    }
+
+    /// Uses the given timeouts instead of the default fixed one.
+    pub fn with_config(mut self, config: ProposerConfig) -> Self {
+        self.timeout.set_config(config);
+        self
+    }
+
+    fn process(&mut self, now: Instant) -> Vec<Msg<Body>> {
+        let messages: Vec<Msg<Body>> = self.inbox.drain(0..).collect();
+        let responses: Vec<Msg<Body>> = messages
+            .into_iter()
+            .flat_map(|m| self.process_msg(m, now))
+            .collect();
+        if !responses.is_empty() {
+            // We made progress, thus returning.
+            return responses;
+        }
+
+        // Check whether we are still within the timeout.
+        if self
+            .state
+            .last_progress_at()
+            .map(|t| !self.timeout.is_expired(t, now))
+            .unwrap_or(true)
+        {
+            return vec![];
+        }
+
+        // We timed out - going to a new classic round.
+        self.timeout.expire();
+
+        let (client, id, value) = self
+            .state
+            .request()
+            .expect("can't be reached from idle state, thus there is a request");
+
+        self.prepare(client, id, value, now)
+    }
+
+    fn process_msg(&mut self, m: Msg<Body>, now: Instant) -> Vec<Msg<Body>> {
+        match m.body {
+            Body::Request(id, v) => self.process_request(m.header.from, id, v, now),
+            Body::Promise(promised_epoch, accepted) => {
+                self.process_promise(m.header.from, promised_epoch, accepted, now)
+            }
+            Body::Accept(epoch, value) => self.process_accept(m.header.from, epoch, value, now),
+            // Duplicated or misrouted messages meant for end-users.
+            Body::Response(_, _) | Body::Busy(_) => vec![],
+            Body::Prepare(_) | Body::FastPropose(_, _) | Body::Propose(_, _) => unimplemented!(),
+        }
+    }
+
+    fn process_request(
+        &mut self,
+        client: Address,
+        id: RequestId,
+        value: Value,
+        now: Instant,
+    ) -> Vec<Msg<Body>> {
+        // A resubmitted request is answered right away once decided, and
+        // dropped while still being handled or queued.
+        if let Some(decided) = self.answered.get(&id).cloned() {
+            return vec![self.respond(client, id, decided, now)];
+        }
+        if self
+            .state
+            .request()
+            .map(|(_, i, _)| i == id)
+            .unwrap_or(false)
+            || self.queue.iter().any(|(_, i, _)| *i == id)
+        {
+            return vec![];
+        }
+
+        match self.state {
+            ProposerState::Fast { .. }
+            | ProposerState::Preparing { .. }
+            | ProposerState::Proposing { .. } => {
+                if self.queue.len() >= self.queue_capacity {
+                    return vec![self.send(client, Body::Busy(id), now)];
+                }
+
+                self.queue.push_back((client, id, value));
+                vec![]
+            }
+            ProposerState::Idle if self.fast_round_over => self.prepare(client, id, value, now),
+            // The end-user proposed the value to the acceptors itself, see
+            // `Body::submit`.
+            ProposerState::Idle => {
+                self.sent_at = now;
+                self.state = ProposerState::Fast {
+                    last_progress_at: now,
+                    client,
+                    id,
+                    value,
+                };
+
+                self.check_fast_round(now)
+            }
+        }
+    }
+
+    fn process_promise(
+        &mut self,
+        acceptor: Address,
+        promised_epoch: Epoch,
+        accepted: Option<(Epoch, Value)>,
+        now: Instant,
+    ) -> Vec<Msg<Body>> {
+        // Ignore any messages outside our current epoch.
+        if promised_epoch != self.epoch {
+            return vec![];
+        }
+
+        let (client, id, value, promises) = match &mut self.state {
+            ProposerState::Preparing {
+                last_progress_at,
+                client,
+                id,
+                value,
+                promises,
+            } => {
+                *last_progress_at = now;
+                // Duplicate promises, e.g. due to a faulty network, are
+                // deduplicated by the map.
+                if promises.insert(acceptor, accepted).is_none() {
+                    self.timeout.observe(now - self.sent_at);
+                }
+
+                if promises.len() < classic_quorum(self.acceptors.len()) {
+                    return vec![];
+                }
+
+                (client.clone(), *id, value.clone(), std::mem::take(promises))
+            }
+            _ => return vec![],
+        };
+
+        let value = self.recover(&promises).unwrap_or(value);
+
+        self.state = ProposerState::Proposing {
+            last_progress_at: now,
+            client,
+            id,
+            value: value.clone(),
+            received_accepts: BTreeSet::new(),
+        };
+
+        self.broadcast_to_acceptors(Body::Propose(self.epoch, value), now)
+    }
+
+    /// Returns the value that might have been chosen in an earlier round
+    /// according to the given promises, if any.
+    fn recover(&self, promises: &BTreeMap<Address, Option<(Epoch, Value)>>) -> Option<Value> {
+        let accepted: Vec<&(Epoch, Value)> = promises.values().flatten().collect();
+        let highest = accepted.iter().map(|(e, _)| *e).max()?;
+
+        // Within a classic round all acceptors accept the same value.
+        if highest != FAST_EPOCH {
+            return accepted
+                .into_iter()
+                .find(|(e, _)| *e == highest)
+                .map(|(_, v)| v.clone());
+        }
+
+        // A value chosen in the fast round was accepted by a fast quorum, thus
+        // by all acceptors that promised but the ones outside that quorum.
+        let n = self.acceptors.len();
+        let threshold = fast_quorum(n) + promises.len() - n;
+        let mut counts: BTreeMap<&Value, usize> = BTreeMap::new();
+        for (_, v) in accepted {
+            *counts.entry(v).or_default() += 1;
+        }
+
+        counts
+            .into_iter()
+            .find(|(_, count)| *count >= threshold)
+            .map(|(v, _)| v.clone())
+    }
+
+    fn process_accept(
+        &mut self,
+        acceptor: Address,
+        epoch: Epoch,
+        accepted: Value,
+        now: Instant,
+    ) -> Vec<Msg<Body>> {
+        let n = self.acceptors.len();
+
+        if epoch == FAST_EPOCH {
+            // Duplicate accepts, e.g. due to a faulty network, are
+            // deduplicated by the map.
+            let new = self.fast_accepts.insert(acceptor, accepted).is_none();
+            if let ProposerState::Fast {
+                last_progress_at, ..
+            } = &mut self.state
+            {
+                *last_progress_at = now;
+                if new {
+                    self.timeout.observe(now - self.sent_at);
+                }
+            }
+
+            return self.check_fast_round(now);
+        }
+
+        match &mut self.state {
+            ProposerState::Proposing {
+                last_progress_at,
+                client,
+                id,
+                value,
+                received_accepts,
+            } if epoch == self.epoch => {
+                *last_progress_at = now;
+                // Duplicate accepts, e.g. due to a faulty network, are
+                // deduplicated by the set.
+                if received_accepts.insert(acceptor) {
+                    self.timeout.observe(now - self.sent_at);
+                }
+
+                if received_accepts.len() < classic_quorum(n) {
+                    return vec![];
+                }
+
+                let (client, id, value) = (client.clone(), *id, value.clone());
+                self.state = ProposerState::Idle;
+                let mut msgs = vec![self.respond(client, id, value, now)];
+                msgs.append(&mut self.dequeue(now));
+                msgs
+            }
+            // Ignore any messages outside our current round.
+            _ => vec![],
+        }
+    }
+
+    /// Responds once a value is chosen in the fast round, or starts a classic
+    /// round on a collision, while handling a request in the fast round.
+    fn check_fast_round(&mut self, now: Instant) -> Vec<Msg<Body>> {
+        let (client, id, value) = match &self.state {
+            ProposerState::Fast {
+                client, id, value, ..
+            } => (client.clone(), *id, value.clone()),
+            _ => return vec![],
+        };
+        let n = self.acceptors.len();
+
+        let mut counts: BTreeMap<&Value, usize> = BTreeMap::new();
+        for v in self.fast_accepts.values() {
+            *counts.entry(v).or_default() += 1;
+        }
+
+        // Not necessarily the requested value, though the end-user learns
+        // about the chosen one either way.
+        if let Some((chosen, _)) = counts.iter().find(|(_, c)| **c >= fast_quorum(n)) {
+            let chosen = (*chosen).clone();
+            self.state = ProposerState::Idle;
+            let mut msgs = vec![self.respond(client, id, chosen, now)];
+            msgs.append(&mut self.dequeue(now));
+            return msgs;
+        }
+
+        // Collision, none of the values, reported or not, can make it to a
+        // fast quorum with the acceptors still to report.
+        let pending = n - self.fast_accepts.len();
+        if pending < fast_quorum(n) && counts.values().all(|c| c + pending < fast_quorum(n)) {
+            return self.prepare(client, id, value, now);
+        }
+
+        vec![]
+    }
+
+    /// Starts a classic round for the given request with a new epoch.
+    fn prepare(
+        &mut self,
+        client: Address,
+        id: RequestId,
+        value: Value,
+        now: Instant,
+    ) -> Vec<Msg<Body>> {
+        self.epoch = Epoch::new(self.epoch.epoch + 1, self.epoch.identifier);
+        self.fast_round_over = true;
+        self.state = ProposerState::Preparing {
+            last_progress_at: now,
+            client,
+            id,
+            value,
+            promises: BTreeMap::new(),
+        };
+
+        self.broadcast_to_acceptors(Body::Prepare(self.epoch), now)
+    }
+
+    /// Starts handling the oldest queued request, if idle.
+    fn dequeue(&mut self, now: Instant) -> Vec<Msg<Body>> {
+        let mut msgs = vec![];
+        while matches!(self.state, ProposerState::Idle) {
+            let (client, id, value) = match self.queue.pop_front() {
+                Some(r) => r,
+                None => break,
+            };
+            msgs.append(&mut self.process_request(client, id, value, now));
+        }
+        msgs
+    }
+
+    /// Responds to the given request, remembering the decided value in case
+    /// it is resubmitted.
+    fn respond(&mut self, client: Address, id: RequestId, value: Value, now: Instant) -> Msg<Body> {
+        self.answered.insert(id, value.clone());
+        self.send(client, Body::Response(id, value), now)
+    }
+
+    fn send(&self, to: Address, body: Body, now: Instant) -> Msg<Body> {
+        Msg {
+            header: Header {
+                from: self.address.clone(),
+                to,
+                at: now + 1,
+            },
+            body,
+        }
+    }
+
+    fn broadcast_to_acceptors(&mut self, b: Body, now: Instant) -> Vec<Msg<Body>> {
+        self.sent_at = now;
+        self.acceptors
+            .iter()
+            .map(|a| Msg {
+                header: Header {
+                    from: self.address.clone(),
+                    to: a.clone(),
+                    at: now + 1,
+                },
+                body: b.clone(),
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+enum ProposerState {
+    Idle,
+    /// Client and value proposed by it in the fast round.
+    Fast {
+        last_progress_at: Instant,
+        client: Address,
+        id: RequestId,
+        value: Value,
+    },
+    /// Value to propose unless another one might have been chosen already,
+    /// and the promises received so far.
+    Preparing {
+        last_progress_at: Instant,
+        client: Address,
+        id: RequestId,
+        value: Value,
+        promises: BTreeMap<Address, Option<(Epoch, Value)>>,
+    },
+    Proposing {
+        last_progress_at: Instant,
+        client: Address,
+        id: RequestId,
+        value: Value,
+        received_accepts: BTreeSet<Address>,
+    },
+}
+
+impl ProposerState {
+    fn last_progress_at(&self) -> Option<Instant> {
+        match self {
+            ProposerState::Idle => None,
+            ProposerState::Fast {
+                last_progress_at, ..
+            }
+            | ProposerState::Preparing {
+                last_progress_at, ..
+            }
+            | ProposerState::Proposing {
+                last_progress_at, ..
+            } => Some(*last_progress_at),
+        }
+    }
+
+    /// Returns the client, the identifier and the value of the request being
+    /// handled.
+    fn request(&self) -> Option<(Address, RequestId, Value)> {
+        match self {
+            ProposerState::Idle => None,
+            ProposerState::Fast {
+                client, id, value, ..
+            }
+            | ProposerState::Preparing {
+                client, id, value, ..
+            }
+            | ProposerState::Proposing {
+                client, id, value, ..
+            } => Some((client.clone(), *id, value.clone())),
+        }
+    }
+}
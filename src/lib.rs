@@ -1,7 +1,14 @@
 use std::cmp::Ord;
 
 pub mod classic;
+pub mod fast;
+pub mod multi;
 pub mod nack;
+pub mod quorum;
+#[cfg(feature = "sim")]
+pub mod sim;
+pub mod storage;
+pub mod timeout;
 
 /// Node represents a networked logical entity, e.g. a proposer or an acceptor.
 pub trait Node<B: Body> {
@@ -9,11 +16,113 @@ pub trait Node<B: Body> {
     /// *not* allowed to do any kind of processing.
     fn receive(&mut self, m: Msg<B>);
     fn process(&mut self, now: Instant) -> Vec<Msg<B>>;
+    /// Crash drops all volatile state, e.g. buffered incoming messages or
+    /// in-flight requests, as if the node was restarted at `now`. State the
+    /// node persisted survives.
+    fn crash(&mut self, now: Instant);
+    /// Returns when the node wants to process next without receiving any
+    /// message, e.g. to check for a timeout. Asked after each call to
+    /// `process`. Nodes are polled every tick by default.
+    fn timer(&self) -> Timer {
+        Timer::Poll
+    }
+}
+
+/// Wake-up requested by a [`Node`], letting e.g. a simulation skip idle
+/// nodes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Timer {
+    /// Process every tick.
+    #[default]
+    Poll,
+    /// Process at the given instant at the latest, e.g. once a timeout
+    /// expires.
+    At(Instant),
+    /// Process only once a message arrives.
+    Idle,
+}
+
+impl Timer {
+    /// Returns whether the node needs to process at the given instant, even
+    /// without a message to receive.
+    pub fn is_due(&self, now: Instant) -> bool {
+        match self {
+            Timer::Poll => true,
+            Timer::At(at) => *at <= now,
+            Timer::Idle => false,
+        }
+    }
+
+    /// Returns the earlier of the two wake-ups.
+    pub fn min(self, other: Timer) -> Timer {
+        match (self, other) {
+            (Timer::Poll, _) | (_, Timer::Poll) => Timer::Poll,
+            (Timer::At(a), Timer::At(b)) => Timer::At(a.min(b)),
+            (Timer::At(at), Timer::Idle) | (Timer::Idle, Timer::At(at)) => Timer::At(at),
+            (Timer::Idle, Timer::Idle) => Timer::Idle,
+        }
+    }
 }
 
-pub trait Proposer<B: Body>: Node<B> {}
+pub trait Proposer<B: Body>: Node<B> {
+    /// Introduces the other proposers of the cluster, e.g. to elect a leader
+    /// among them. Ignored by default.
+    fn set_peers(&mut self, _peers: Vec<Address>) {}
+    /// Sets the quorum system consulted in each phase, see [`quorum`].
+    /// Proposers with a quorum rule of their own, e.g. [`fast::Proposer`],
+    /// ignore it.
+    fn set_quorums(&mut self, _quorums: Box<dyn quorum::QuorumSystem>) {}
+    /// Introduces the learners of the cluster to notify according to the
+    /// given strategy. Ignored by default.
+    fn set_learners(&mut self, _learners: Vec<Address>, _learning: Learning) {}
+    /// Sets the timeouts of the proposer, see [`timeout::ProposerConfig`].
+    fn set_config(&mut self, config: timeout::ProposerConfig);
+    /// Returns the timeouts of the proposer.
+    fn config(&self) -> timeout::ProposerConfig;
+}
+
+pub trait Acceptor<B: Body>: Node<B> {
+    /// Returns the current promised and accepted epochs of each log slot the
+    /// acceptor knows about, e.g. to check invariants across acceptors.
+    /// Single-decree acceptors only use slot 0.
+    fn states(&self) -> std::collections::BTreeMap<Slot, storage::AcceptorState>;
+    /// Amnesia crashes the acceptor and has it lose its persisted state too,
+    /// restarting blank. Only meant for fault injection, as it breaks the
+    /// assumptions Paxos relies on.
+    fn amnesia(&mut self, now: Instant);
+    /// Introduces the learners of the cluster to notify according to the
+    /// given strategy. Ignored by default.
+    fn set_learners(&mut self, _learners: Vec<Address>, _learning: Learning) {}
+}
+
+/// Learner finds out about chosen values, either by hearing from a quorum of
+/// acceptors or by being told by another node, see [`Learning`].
+pub trait Learner<B: Body>: Node<B> + std::fmt::Debug {
+    /// Returns the value learned in each slot so far.
+    fn learned(&self) -> std::collections::BTreeMap<Slot, Value>;
+    /// Sets the quorum system telling which acceptors need to have accepted
+    /// a value for it to be chosen, see [`quorum`]. Ignored by default.
+    fn set_quorums(&mut self, _quorums: Box<dyn quorum::QuorumSystem>) {}
+    /// Introduces all learners of the cluster, including this one, and the
+    /// strategy they learn by. Ignored by default.
+    fn set_learners(&mut self, _learners: Vec<Address>, _learning: Learning) {}
+}
 
-pub trait Acceptor<B: Body>: Node<B> {}
+/// Strategies for learners to find out about chosen values, trading off
+/// messages against latency.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Learning {
+    /// The proposer tells all learners once a quorum of acceptors accepted
+    /// its value. Takes a message per learner.
+    Proposer,
+    /// Acceptors notify the first learner only, which tells all others once
+    /// it learned a value. Takes a message per acceptor and learner, though
+    /// an additional message delay.
+    Distinguished,
+    /// Acceptors notify all learners. Takes a message per acceptor for each
+    /// learner.
+    AllToAll,
+}
 
 #[derive(Clone, PartialEq, Eq)]
 pub struct Msg<B: Body> {
@@ -53,11 +162,45 @@ impl std::fmt::Debug for Header {
 }
 
 pub trait Body: Eq + std::fmt::Debug + Clone {
-    fn is_request(&self) -> Option<Value>;
-    fn is_response(&self) -> Option<Value>;
+    /// Returns a request by an end-user for the given value. A request
+    /// resubmitted, e.g. after a timeout, carries the same identifier.
+    fn new_request(id: RequestId, v: Value) -> Self;
+    fn is_request(&self) -> Option<(RequestId, Value)>;
+    /// Returns the identifier of the request responded to along with the
+    /// decided value.
+    fn is_response(&self) -> Option<(RequestId, Value)>;
+    /// Returns the identifier of a request a proposer refused to handle,
+    /// e.g. due to overload. Never the case by default.
+    fn is_rejection(&self) -> Option<RequestId> {
+        None
+    }
+    /// Returns the messages an end-user sends to submit the given request to
+    /// its proposer, by default only the request itself. Lets protocols have
+    /// end-users propose to the acceptors right away, see [`fast`].
+    fn submit(request: Msg<Self>, _acceptors: &[Address]) -> Vec<Msg<Self>> {
+        vec![request]
+    }
+    /// Returns the epoch and value of a proposal to an acceptor.
+    fn is_propose(&self) -> Option<(Epoch, Value)>;
+    /// Returns the log slot a proposal or response refers to. Single-decree
+    /// protocols only ever decide slot 0.
+    fn slot(&self) -> Slot {
+        Slot(0)
+    }
+    /// Returns whether a value accepted by the given acceptors in the given
+    /// epoch is chosen, out of all acceptors. Defaults to a phase-2 quorum of
+    /// the given quorum system.
+    fn is_chosen(
+        _epoch: Epoch,
+        acceptors: &[Address],
+        accepted: &std::collections::BTreeSet<Address>,
+        quorums: &dyn quorum::QuorumSystem,
+    ) -> bool {
+        quorums.is_phase2_quorum(acceptors, accepted)
+    }
 }
 
-#[derive(Eq, Hash, Clone, Default, PartialOrd, PartialEq)]
+#[derive(Eq, Hash, Clone, Default, PartialOrd, PartialEq, Ord)]
 pub struct Address(String);
 
 impl std::cmp::PartialEq<&str> for Address {
@@ -113,7 +256,7 @@ impl std::ops::Sub for Instant {
 
 /// Epoch is a tuple of an increasing epoch counter and a proposer specific
 /// identifier to partition the global epoch set among proposers.
-#[derive(Clone, Copy, Default, PartialOrd, PartialEq, Eq)]
+#[derive(Clone, Copy, Default, PartialOrd, PartialEq, Eq, Ord)]
 pub struct Epoch {
     pub epoch: u32,
     pub identifier: u32,
@@ -131,6 +274,27 @@ impl Epoch {
     }
 }
 
+/// Position in a replicated log, see [`multi`].
+#[derive(Clone, Copy, Default, PartialOrd, PartialEq, Eq, Ord)]
+pub struct Slot(pub u64);
+
+impl std::fmt::Debug for Slot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:?}", self.0)
+    }
+}
+
+/// Identifies a request by an end-user, unique across all end-users.
+/// Proposers answer a resubmitted request only once.
+#[derive(Clone, Copy, Default, PartialOrd, PartialEq, Eq, Ord)]
+pub struct RequestId(pub u64);
+
+impl std::fmt::Debug for RequestId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "r{:?}", self.0)
+    }
+}
+
 #[derive(Clone, Default, PartialOrd, PartialEq, Ord, Eq)]
 pub struct Value(String);
 
@@ -0,0 +1,232 @@
+use super::{Body, LogState};
+use crate::storage::{AcceptorState, MemoryStorage, Storage};
+use crate::{Address, Epoch, Header, Instant, Msg, Node, Slot, Timer};
+use std::collections::{BTreeMap, VecDeque};
+
+/// An acceptor tracking a single promise across all slots and the accepted
+/// command of each slot.
+#[derive(Clone, Debug)]
+pub struct Acceptor {
+    address: Address,
+    state: LogState,
+    storage: Box<dyn Storage<LogState>>,
+    /// Whether to reply to proposers before the state is durable. Unsafe,
+    /// only useful to demonstrate why persisting first matters.
+    reply_before_persisting: bool,
+    inbox: VecDeque<Msg<Body>>,
+    /// Replies waiting for the state they depend on to be durable.
+    outbox: Vec<(Instant, Msg<Body>)>,
+    /// Duration of leases granted, see [`Acceptor::with_lease`].
+    lease: Option<Instant>,
+    /// Epoch holding the current lease and the instant it expires at.
+    leased: Option<(Epoch, Instant)>,
+}
+
+impl Node<Body> for Acceptor {
+    fn receive(&mut self, m: Msg<Body>) {
+        self.inbox.push_back(m);
+    }
+
+    fn process(&mut self, now: Instant) -> Vec<Msg<Body>> {
+        self.process(now)
+    }
+
+    fn crash(&mut self, now: Instant) {
+        self.inbox.clear();
+        self.outbox.clear();
+        self.storage.crash(now);
+        self.state = self.storage.read().expect("to read persisted state");
+    }
+
+    fn timer(&self) -> Timer {
+        // Replies held back until the state they depend on is durable.
+        match self.outbox.iter().map(|(durable_at, _)| *durable_at).min() {
+            Some(at) => Timer::At(at),
+            None => Timer::Idle,
+        }
+    }
+}
+
+impl crate::Acceptor<Body> for Acceptor {
+    fn states(&self) -> BTreeMap<Slot, AcceptorState> {
+        self.state
+            .accepted
+            .iter()
+            .map(|(slot, (epoch, command))| {
+                let state = AcceptorState {
+                    promised_epoch: self.state.promised_epoch,
+                    accepted: Some((*epoch, command.monitored())),
+                };
+                (*slot, state)
+            })
+            .collect()
+    }
+
+    fn amnesia(&mut self, now: Instant) {
+        self.crash(now);
+        self.storage.wipe().expect("to wipe persisted state");
+        self.state = LogState::default();
+        self.leased = None;
+    }
+}
+
+impl Acceptor {
+    pub fn new(address: Address) -> Self {
+        Acceptor::with_storage(address, Box::new(MemoryStorage::default()))
+    }
+
+    pub fn with_storage(address: Address, storage: Box<dyn Storage<LogState>>) -> Self {
+        Acceptor {
+            address,
+            state: storage.read().expect("to read persisted state"),
+            storage,
+            reply_before_persisting: false,
+            inbox: VecDeque::new(),
+            outbox: vec![],
+            lease: None,
+            leased: None,
+        }
+    }
+
+    /// Reply to proposers without waiting for the state to be durable. This
+    /// violates safety in the face of crashes.
+    pub fn reply_before_persisting(mut self) -> Self {
+        self.reply_before_persisting = true;
+        self
+    }
+
+    /// Grants a lease of the given duration along with each promise and each
+    /// renewal, refusing to promise any other epoch until it expired. Must
+    /// match the duration configured with [`super::Proposer::with_lease`].
+    ///
+    /// Relies on all nodes sharing a clock, as is the case in the simulation.
+    pub fn with_lease(mut self, duration: Instant) -> Self {
+        self.lease = Some(duration);
+        self
+    }
+
+    pub fn address(&self) -> Address {
+        self.address.clone()
+    }
+
+    pub fn process(&mut self, now: Instant) -> Vec<Msg<Body>> {
+        let (durable, outbox): (Vec<_>, Vec<_>) = self
+            .outbox
+            .drain(0..)
+            .partition(|(durable_at, _)| *durable_at <= now);
+        self.outbox = outbox;
+
+        let messages: Vec<Msg<Body>> = self.inbox.drain(0..).collect();
+        durable
+            .into_iter()
+            .map(|(_, mut m)| {
+                m.header.at = now + 1;
+                m
+            })
+            .chain(messages.into_iter().flat_map(|m| self.process_msg(m, now)))
+            .collect()
+    }
+
+    fn process_msg(&mut self, m: Msg<Body>, now: Instant) -> Vec<Msg<Body>> {
+        match m.body {
+            Body::Prepare(i) => {
+                if self.state.promised_epoch.map(|e| e > i).unwrap_or(false) {
+                    return vec![];
+                }
+
+                // Another leader holds a lease, thus it might be serving
+                // reads without knowing about values chosen in higher epochs.
+                // The leader itself moving on to a higher epoch gives up its
+                // lease instead.
+                if let Some((holder, until)) = self.leased {
+                    if holder.identifier != i.identifier && now < until {
+                        return vec![];
+                    }
+                }
+
+                if let Some(lease) = self.lease {
+                    self.leased = Some((i, now + lease));
+                }
+
+                let mut state = self.state.clone();
+                state.promised_epoch = Some(i);
+                let body = Body::Promise(i, state.accepted.clone());
+
+                self.persist_and_reply(state, m.header.from, body, now)
+            }
+            Body::Propose(proposed_epoch, slot, command) => {
+                if self
+                    .state
+                    .promised_epoch
+                    .map(|e| e > proposed_epoch)
+                    .unwrap_or(false)
+                {
+                    return vec![];
+                }
+
+                // Accepting implies promising, otherwise a proposal of a
+                // lower epoch could still replace the accepted command.
+                let mut state = self.state.clone();
+                state.promised_epoch = Some(proposed_epoch);
+                state.accepted.insert(slot, (proposed_epoch, command));
+
+                self.persist_and_reply(
+                    state,
+                    m.header.from,
+                    Body::Accept(proposed_epoch, slot),
+                    now,
+                )
+            }
+            Body::Renew(e, at) => {
+                let lease = match self.lease {
+                    Some(lease) if self.state.promised_epoch == Some(e) => lease,
+                    _ => return vec![],
+                };
+
+                self.leased = Some((e, now + lease));
+
+                vec![Msg {
+                    header: Header {
+                        from: self.address.clone(),
+                        to: m.header.from,
+                        at: now + 1,
+                    },
+                    body: Body::Renewed(e, at),
+                }]
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    /// Persist the given state and reply to the proposer once it is durable.
+    /// Failing to persist the state results in no reply at all.
+    fn persist_and_reply(
+        &mut self,
+        state: LogState,
+        to: Address,
+        body: Body,
+        now: Instant,
+    ) -> Vec<Msg<Body>> {
+        let durable_at = match self.storage.write(&state, now) {
+            Ok(durable_at) => durable_at,
+            Err(_) => return vec![],
+        };
+        self.state = state;
+
+        let reply = Msg {
+            header: Header {
+                from: self.address.clone(),
+                to,
+                at: now + 1,
+            },
+            body,
+        };
+
+        if durable_at <= now || self.reply_before_persisting {
+            return vec![reply];
+        }
+
+        self.outbox.push((durable_at, reply));
+        vec![]
+    }
+}
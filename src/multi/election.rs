@@ -0,0 +1,59 @@
+use crate::{Address, Instant};
+use std::collections::BTreeMap;
+
+/// Interval between two heartbeats of a proposer.
+const HEARTBEAT_INTERVAL: Instant = Instant(5);
+/// A peer not heard from for this long is considered down.
+const ELECTION_TIMEOUT: Instant = Instant(20);
+
+/// Tracks which proposers are alive through heartbeats. The proposer with the
+/// lowest address among them is the leader.
+#[derive(Clone, Debug, Default)]
+pub(super) struct Election {
+    /// Instant the election started at. Peers are assumed to be alive at
+    /// that point, thus proposers agree on the same leader right away.
+    since: Instant,
+    /// Last instant each peer was heard from.
+    heard_from: BTreeMap<Address, Instant>,
+    last_heartbeat_at: Option<Instant>,
+}
+
+impl Election {
+    /// Starts over, e.g. after a crash.
+    pub fn restart(&mut self, now: Instant) {
+        *self = Election {
+            since: now,
+            ..Default::default()
+        }
+    }
+
+    pub fn heard_from(&mut self, peer: Address, now: Instant) {
+        self.heard_from.insert(peer, now);
+    }
+
+    /// Returns whether a heartbeat is due, assuming it to be sent if so.
+    pub fn heartbeat_due(&mut self, now: Instant) -> bool {
+        if let Some(last_heartbeat_at) = self.last_heartbeat_at {
+            if now < last_heartbeat_at + HEARTBEAT_INTERVAL {
+                return false;
+            }
+        }
+
+        self.last_heartbeat_at = Some(now);
+        true
+    }
+
+    /// Returns the lowest address among the given proposer and the peers
+    /// heard from within the election timeout.
+    pub fn leader<'a>(&self, me: &'a Address, peers: &'a [Address], now: Instant) -> &'a Address {
+        peers
+            .iter()
+            .filter(|p| {
+                let last = self.heard_from.get(p).copied().unwrap_or(self.since);
+                now < last + ELECTION_TIMEOUT
+            })
+            .chain(std::iter::once(me))
+            .min()
+            .unwrap()
+    }
+}
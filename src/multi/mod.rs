@@ -0,0 +1,153 @@
+//! Multi-Paxos, deciding a replicated log of values instead of a single one.
+//!
+//! Proposers assign requests to increasing log [`Slot`]s. Once a proposer ran
+//! phase 1 for its epoch it leads all slots, proposing subsequent requests
+//! right away without another round of prepares until it is superseded.
+//!
+//! Optionally proposers elect a leader among themselves through heartbeats,
+//! forwarding requests to it instead of duelling, see
+//! [`Proposer::with_election`]. On top the leader can hold a lease granted by
+//! the acceptors, serving reads locally, see [`Proposer::with_lease`].
+
+use crate::{Address, Epoch, Instant, RequestId, Slot, Value};
+use std::collections::BTreeMap;
+
+pub use acceptor::Acceptor;
+pub use proposer::Proposer;
+
+mod acceptor;
+mod election;
+mod proposer;
+
+#[derive(Clone, PartialEq, Eq)]
+pub enum Body {
+    /// Request by an end-user.
+    Request(RequestId, Value),
+    /// Response by a proposer to an end-user, with the slot the requested
+    /// value was chosen in.
+    Response(RequestId, Slot, Value),
+    /// Request forwarded to the leader on behalf of the given end-user, see
+    /// [`Proposer::with_election`].
+    Forward(Address, RequestId, Value),
+    /// Prepare covers all slots at once.
+    Prepare(Epoch),
+    /// Promised epoch and the accepted epoch and command of every slot.
+    Promise(Epoch, BTreeMap<Slot, (Epoch, Command)>),
+    Propose(Epoch, Slot, Command),
+    Accept(Epoch, Slot),
+    /// Sent periodically by each proposer to its peers, see
+    /// [`Proposer::with_election`].
+    Heartbeat,
+    /// Request by a leader to extend its lease, carrying the instant it was
+    /// sent at.
+    Renew(Epoch, Instant),
+    /// Lease extension granted by an acceptor, echoing the instant of the
+    /// request.
+    Renewed(Epoch, Instant),
+}
+
+/// State a multi-Paxos acceptor needs to persist before replying to a
+/// proposer, see [`crate::storage::Storage`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LogState {
+    /// Single promise covering all slots.
+    pub promised_epoch: Option<Epoch>,
+    /// Accepted epoch and command of each slot.
+    pub accepted: BTreeMap<Slot, (Epoch, Command)>,
+}
+
+/// Entry of the log proposed for a slot.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Command {
+    /// Value requested by an end-user, along with the identifier of the
+    /// request, telling apart requests of equal values.
+    Request(RequestId, Value),
+    /// Fills a slot none of a quorum of acceptors accepted a command for when
+    /// taking over the log.
+    Noop,
+}
+
+impl Command {
+    /// Returns the requested value, none for a no-op.
+    pub fn value(&self) -> Option<&Value> {
+        match self {
+            Command::Request(_, v) => Some(v),
+            Command::Noop => None,
+        }
+    }
+
+    /// Returns the value as reported to observers comparing values only, see
+    /// [`crate::Acceptor::states`], the default value for a no-op.
+    fn monitored(&self) -> Value {
+        self.value().cloned().unwrap_or_default()
+    }
+}
+
+impl std::fmt::Debug for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Command::Request(id, v) => write!(f, "{:?} {:?}", id, v),
+            Command::Noop => write!(f, "no-op"),
+        }
+    }
+}
+
+impl crate::Body for Body {
+    fn new_request(id: RequestId, v: Value) -> Self {
+        Body::Request(id, v)
+    }
+
+    fn is_request(&self) -> Option<(RequestId, Value)> {
+        if let Body::Request(id, v) = self {
+            return Some((*id, v.clone()));
+        }
+
+        None
+    }
+
+    fn is_response(&self) -> Option<(RequestId, Value)> {
+        if let Body::Response(id, _, v) = self {
+            return Some((*id, v.clone()));
+        }
+
+        None
+    }
+
+    fn is_propose(&self) -> Option<(Epoch, Value)> {
+        if let Body::Propose(e, _, c) = self {
+            return Some((*e, c.monitored()));
+        }
+
+        None
+    }
+
+    fn slot(&self) -> Slot {
+        match self {
+            Body::Response(_, s, _) | Body::Propose(_, s, _) | Body::Accept(_, s) => *s,
+            Body::Request(_, _)
+            | Body::Forward(_, _, _)
+            | Body::Prepare(_)
+            | Body::Promise(_, _)
+            | Body::Heartbeat
+            | Body::Renew(_, _)
+            | Body::Renewed(_, _) => Slot(0),
+        }
+    }
+}
+
+impl std::fmt::Debug for Body {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Body::Request(id, v) => write!(f, "request({:?}, {:?})", id, v),
+            Body::Response(id, s, v) => write!(f, "response({:?}, {:?}, {:?})", id, s, v),
+            Body::Forward(c, id, v) => write!(f, "forward({:?}, {:?}, {:?})", c, id, v),
+            Body::Prepare(e) => write!(f, "prepare({:?})", e),
+            Body::Promise(e, a) => write!(f, "promise({:?}, {:?})", e, a),
+            Body::Propose(e, s, v) => write!(f, "propose({:?}, {:?}, {:?})", e, s, v),
+            Body::Accept(e, s) => write!(f, "accept({:?}, {:?})", e, s),
+            Body::Heartbeat => write!(f, "heartbeat"),
+            Body::Renew(e, at) => write!(f, "renew({:?}, {:?})", e, at),
+            Body::Renewed(e, at) => write!(f, "renewed({:?}, {:?})", e, at),
+        }
+    }
+}
@@ -0,0 +1,646 @@
+use super::election::Election;
+use super::{Body, Command};
+use crate::quorum::QuorumSystem;
+use crate::timeout::{ProposerConfig, Timeout};
+use crate::{Address, Epoch, Header, Instant, Msg, Node, RequestId, Slot, Timer, Value};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+/// A proposer appending requested values to a replicated log.
+///
+/// Once it gathered a quorum of promises for its epoch it leads all slots,
+/// proposing each further request in the next free slot right away. It only
+/// runs phase 1 again once it times out waiting for accepts, e.g. because
+/// another proposer took over with a higher epoch.
+#[derive(Clone, Debug)]
+pub struct Proposer {
+    address: Address,
+    pub acceptors: Vec<Address>,
+    /// The other proposers of the cluster.
+    peers: Vec<Address>,
+    inbox: VecDeque<Msg<Body>>,
+    epoch: Epoch,
+    state: ProposerState,
+    /// Requests not yet proposed in any slot.
+    queue: VecDeque<(RequestId, Value)>,
+    /// Requested values not yet responded to, along with the end-user that
+    /// requested each and the identifier of the request.
+    outstanding: Vec<(Address, RequestId, Value)>,
+    /// Slot and value of each request this proposer knows to be chosen,
+    /// including those recovered from a previous leader, answering a
+    /// resubmitted request right away instead of appending it again.
+    answered: BTreeMap<RequestId, (Slot, Value)>,
+    /// Proposals of the current epoch waiting for a quorum of accepts.
+    proposals: BTreeMap<Slot, Proposal>,
+    next_slot: Slot,
+    /// Slots this proposer knows to be chosen.
+    chosen: BTreeMap<Slot, Command>,
+    /// Only set when taking part in leader election, see
+    /// [`Proposer::with_election`].
+    election: Option<Election>,
+    /// Only set when holding leases, see [`Proposer::with_lease`].
+    lease: Option<Lease>,
+    quorums: Box<dyn QuorumSystem>,
+    timeout: Timeout,
+}
+
+impl Node<Body> for Proposer {
+    fn receive(&mut self, m: Msg<Body>) {
+        self.inbox.push_back(m);
+    }
+
+    fn process(&mut self, now: Instant) -> Vec<Msg<Body>> {
+        self.process(now)
+    }
+
+    fn crash(&mut self, now: Instant) {
+        self.inbox.clear();
+        self.state = ProposerState::Idle;
+        self.queue.clear();
+        self.outstanding.clear();
+        self.answered.clear();
+        self.proposals.clear();
+        self.next_slot = Slot(0);
+        self.chosen.clear();
+        if let Some(election) = self.election.as_mut() {
+            election.restart(now);
+        }
+        if let Some(lease) = self.lease.as_mut() {
+            lease.expire();
+        }
+        // Round trips observed before the crash are forgotten.
+        self.timeout.restart();
+        // The epoch is persisted. Moving on to the next one prevents reusing
+        // an epoch for a different value after the crash.
+        self.epoch = Epoch::new(self.epoch.epoch + 1, self.epoch.identifier);
+    }
+
+    fn timer(&self) -> Timer {
+        // Heartbeats, changes of leadership and lease renewals are checked
+        // every tick.
+        if self.election.is_some() || self.lease.is_some() {
+            return Timer::Poll;
+        }
+
+        match self.last_progress_at() {
+            Some(t) => Timer::At(t + self.timeout.get()),
+            None => Timer::Idle,
+        }
+    }
+}
+
+impl crate::Proposer<Body> for Proposer {
+    fn set_peers(&mut self, peers: Vec<Address>) {
+        self.peers = peers;
+    }
+
+    fn set_quorums(&mut self, quorums: Box<dyn QuorumSystem>) {
+        self.quorums = quorums;
+    }
+
+    fn set_config(&mut self, config: ProposerConfig) {
+        self.timeout.set_config(config);
+    }
+
+    fn config(&self) -> ProposerConfig {
+        self.timeout.config()
+    }
+}
+
+impl Proposer {
+    pub fn new(address: Address, initial_epoch: Epoch, acceptors: Vec<Address>) -> Self {
+        Self {
+            address,
+            acceptors,
+            peers: vec![],
+            inbox: Default::default(),
+            epoch: initial_epoch,
+            state: ProposerState::Idle,
+            queue: VecDeque::new(),
+            outstanding: vec![],
+            answered: BTreeMap::new(),
+            proposals: BTreeMap::new(),
+            next_slot: Slot(0),
+            chosen: BTreeMap::new(),
+            election: None,
+            lease: None,
+            quorums: Default::default(),
+            timeout: Timeout::new(ProposerConfig::default(), initial_epoch.identifier as u64),
+        }
+    }
+
+    /// Exchanges heartbeats with the other proposers, electing the one with
+    /// the lowest address among those alive as the leader. Requests are
+    /// forwarded to the leader instead of competing for the log.
+    ///
+    /// Requests are forwarded on a best-effort basis, thus lost if the
+    /// forwarded message is dropped or the leader crashes before responding.
+    /// A request resubmitted after a change of leadership is answered from
+    /// the log if the new leader recovered it, instead of being appended
+    /// again.
+    pub fn with_election(mut self) -> Self {
+        self.election = Some(Election::default());
+        self
+    }
+
+    /// Holds a lease of the given duration once leading, renewing it in the
+    /// background, see [`Proposer::read`]. Requires the acceptors to grant
+    /// leases of the same duration, see [`super::Acceptor::with_lease`].
+    pub fn with_lease(mut self, duration: Instant) -> Self {
+        self.lease = Some(Lease {
+            duration,
+            until: None,
+            renewal: None,
+        });
+        self
+    }
+
+    /// Uses the given timeouts instead of the default fixed one.
+    pub fn with_config(mut self, config: ProposerConfig) -> Self {
+        self.timeout.set_config(config);
+        self
+    }
+
+    /// Returns the chosen slots this proposer knows about.
+    pub fn chosen(&self) -> &BTreeMap<Slot, Command> {
+        &self.chosen
+    }
+
+    /// Returns the proposer this proposer considers the leader, itself if it
+    /// does not take part in leader election.
+    pub fn leader(&self, now: Instant) -> &Address {
+        match &self.election {
+            Some(election) => election.leader(&self.address, &self.peers, now),
+            None => &self.address,
+        }
+    }
+
+    /// Returns the values of the log in slot order without contacting any
+    /// acceptor, leaving out no-ops.
+    ///
+    /// Only possible while leading under a lease and once all proposals are
+    /// chosen. No other proposer can have a value chosen while the lease
+    /// holds, thus the read is linearizable.
+    pub fn read(&self, now: Instant) -> Option<Vec<Value>> {
+        let until = self.lease.as_ref().and_then(|l| l.until)?;
+        let leading = matches!(self.state, ProposerState::Leading { .. });
+        if !leading || now >= until || !self.proposals.is_empty() {
+            return None;
+        }
+
+        Some(
+            self.chosen
+                .values()
+                .filter_map(|c| c.value())
+                .cloned()
+                .collect(),
+        )
+    }
+
+    fn process(&mut self, now: Instant) -> Vec<Msg<Body>> {
+        let mut msgs = self.advance(now);
+        msgs.append(&mut self.heartbeat(now));
+        msgs.append(&mut self.renew_lease(now));
+        msgs
+    }
+
+    /// Processes the inbox, falling back to checking for a timeout if that
+    /// did not make any progress.
+    fn advance(&mut self, now: Instant) -> Vec<Msg<Body>> {
+        let messages: Vec<Msg<Body>> = self.inbox.drain(0..).collect();
+        let responses: Vec<Msg<Body>> = messages
+            .into_iter()
+            .flat_map(|m| self.process_msg(m, now))
+            .collect();
+        if !responses.is_empty() {
+            // We made progress, thus returning.
+            return responses;
+        }
+
+        let last_progress_at = match self.last_progress_at() {
+            Some(t) => t,
+            None => return vec![],
+        };
+
+        // Check whether we are still within the timeout.
+        if !self.timeout.is_expired(last_progress_at, now) {
+            return vec![];
+        }
+
+        // We timed out - going back to preparing.
+        self.timeout.expire();
+
+        self.epoch = Epoch::new(self.epoch.epoch + 1, self.epoch.identifier);
+
+        // Proposals of the previous epoch might still have been chosen, which
+        // phase 1 finds out about. Until then the requests are queued again.
+        let proposals = std::mem::take(&mut self.proposals);
+        let mut queue: VecDeque<(RequestId, Value)> = proposals
+            .into_values()
+            .filter_map(|p| match p.command {
+                Command::Request(id, v) => Some((id, v)),
+                Command::Noop => None,
+            })
+            .filter(|(id, _)| self.outstanding.iter().any(|(_, o, _)| o == id))
+            .collect();
+        queue.append(&mut self.queue);
+        self.queue = queue;
+
+        self.prepare(now)
+    }
+
+    fn process_msg(&mut self, m: Msg<Body>, now: Instant) -> Vec<Msg<Body>> {
+        match m.body {
+            Body::Request(id, v) => self.process_request(m.header.from, id, v, now),
+            Body::Forward(client, id, v) => self.process_request(client, id, v, now),
+            Body::Promise(promised_epoch, accepted) => {
+                self.process_promise(m.header.from, promised_epoch, accepted, now)
+            }
+            Body::Accept(epoch, slot) => self.process_accept(m.header.from, epoch, slot, now),
+            Body::Heartbeat => {
+                if let Some(election) = self.election.as_mut() {
+                    election.heard_from(m.header.from, now);
+                }
+                vec![]
+            }
+            Body::Renewed(epoch, sent_at) => {
+                self.process_renewed(m.header.from, epoch, sent_at);
+                vec![]
+            }
+            // Duplicated or misrouted messages meant for end-users.
+            Body::Response(_, _, _) => vec![],
+            Body::Prepare(_) | Body::Propose(_, _, _) | Body::Renew(_, _) => unimplemented!(),
+        }
+    }
+
+    fn process_request(
+        &mut self,
+        client: Address,
+        id: RequestId,
+        value: Value,
+        now: Instant,
+    ) -> Vec<Msg<Body>> {
+        let leader = self.leader(now).clone();
+        if leader != self.address {
+            return vec![self.send(leader, Body::Forward(client, id, value), now)];
+        }
+
+        // A resubmitted request is answered right away once chosen, and
+        // dropped while still in flight. A request recovered from a previous
+        // leader is answered once chosen.
+        if let Some((slot, chosen)) = self.answered.get(&id) {
+            return vec![self.send(client, Body::Response(id, *slot, chosen.clone()), now)];
+        }
+        if self.outstanding.iter().any(|(_, i, _)| *i == id) {
+            return vec![];
+        }
+        let proposed = self
+            .proposals
+            .values()
+            .any(|p| matches!(&p.command, Command::Request(i, _) if *i == id));
+        if proposed {
+            self.outstanding.push((client, id, value));
+            return vec![];
+        }
+
+        self.outstanding.push((client, id, value.clone()));
+        self.queue.push_back((id, value));
+
+        match self.state {
+            ProposerState::Idle => self.prepare(now),
+            ProposerState::Preparing { .. } => vec![],
+            ProposerState::Leading { .. } => self.propose_queued(now),
+        }
+    }
+
+    /// Returns the instant of the last progress while waiting for acceptors,
+    /// thus subject to the timeout.
+    fn last_progress_at(&self) -> Option<Instant> {
+        match self.state {
+            ProposerState::Preparing {
+                last_progress_at, ..
+            } => Some(last_progress_at),
+            // A leader without any proposals in flight is not waiting for
+            // anything.
+            ProposerState::Leading { last_progress_at } if !self.proposals.is_empty() => {
+                Some(last_progress_at)
+            }
+            ProposerState::Idle | ProposerState::Leading { .. } => None,
+        }
+    }
+
+    fn process_promise(
+        &mut self,
+        acceptor: Address,
+        promised_epoch: Epoch,
+        accepted: BTreeMap<Slot, (Epoch, Command)>,
+        now: Instant,
+    ) -> Vec<Msg<Body>> {
+        // Ignore any messages outside our current epoch.
+        if promised_epoch != self.epoch {
+            return vec![];
+        }
+
+        let (started_at, promises) = match &mut self.state {
+            ProposerState::Idle | ProposerState::Leading { .. } => return vec![],
+            ProposerState::Preparing {
+                started_at,
+                last_progress_at,
+                promises,
+            } => {
+                *last_progress_at = now;
+                // Duplicate promises, e.g. due to a faulty network, are
+                // deduplicated by the map.
+                if promises.insert(acceptor, accepted).is_none() {
+                    self.timeout.observe(now - *started_at);
+                }
+
+                let promised: BTreeSet<Address> = promises.keys().cloned().collect();
+                if !self.quorums.is_phase1_quorum(&self.acceptors, &promised) {
+                    return vec![];
+                }
+
+                (*started_at, std::mem::take(promises))
+            }
+        };
+
+        // Each acceptor granted its lease no earlier than the prepare was
+        // sent. Only a phase-2 quorum of grants intersects every phase-1
+        // quorum, thus blocking other proposers. Otherwise the lease only
+        // takes effect once renewed.
+        let promised: BTreeSet<Address> = promises.keys().cloned().collect();
+        let granted = self.quorums.is_phase2_quorum(&self.acceptors, &promised);
+        if let Some(lease) = self.lease.as_mut().filter(|_| granted) {
+            lease.until = Some(started_at + lease.duration);
+        }
+
+        self.lead(promises, now)
+    }
+
+    /// Takes over all slots with a quorum of promises, proposing the
+    /// highest accepted command of each slot again.
+    fn lead(
+        &mut self,
+        promises: BTreeMap<Address, BTreeMap<Slot, (Epoch, Command)>>,
+        now: Instant,
+    ) -> Vec<Msg<Body>> {
+        let mut highest_accepted: BTreeMap<Slot, (Epoch, Command)> = BTreeMap::new();
+        for (slot, (e, c)) in promises.into_values().flatten() {
+            match highest_accepted.get(&slot) {
+                Some((h_e, _)) if *h_e > e => {}
+                _ => {
+                    highest_accepted.insert(slot, (e, c));
+                }
+            }
+        }
+
+        self.state = ProposerState::Leading {
+            last_progress_at: now,
+        };
+
+        let end = highest_accepted
+            .keys()
+            .last()
+            .map(|s| s.0 + 1)
+            .unwrap_or(0)
+            .max(self.next_slot.0);
+        self.next_slot = Slot(end);
+
+        let mut msgs = vec![];
+        for slot in (0..end).map(Slot) {
+            if self.chosen.contains_key(&slot) {
+                continue;
+            }
+
+            // A slot none of the quorum accepted a command for can not have
+            // been chosen, thus filling the gap with a no-op.
+            let command = highest_accepted
+                .remove(&slot)
+                .map(|(_, c)| c)
+                .unwrap_or(Command::Noop);
+            if let Command::Request(id, _) = &command {
+                self.queue.retain(|(i, _)| i != id);
+            }
+            msgs.append(&mut self.propose(slot, command, now));
+        }
+
+        msgs.append(&mut self.propose_queued(now));
+        msgs
+    }
+
+    fn process_accept(
+        &mut self,
+        acceptor: Address,
+        epoch: Epoch,
+        slot: Slot,
+        now: Instant,
+    ) -> Vec<Msg<Body>> {
+        // Ignore any messages outside our current epoch.
+        if epoch != self.epoch {
+            return vec![];
+        }
+
+        let proposal = match self.proposals.get_mut(&slot) {
+            Some(p) => p,
+            None => return vec![],
+        };
+
+        // Duplicate accepts, e.g. due to a faulty network, are deduplicated by
+        // the set.
+        if proposal.accepts.insert(acceptor) {
+            self.timeout.observe(now - proposal.proposed_at);
+        }
+        if let ProposerState::Leading { last_progress_at } = &mut self.state {
+            *last_progress_at = now;
+        }
+
+        if !self
+            .quorums
+            .is_phase2_quorum(&self.acceptors, &proposal.accepts)
+        {
+            return vec![];
+        }
+
+        let command = self.proposals.remove(&slot).unwrap().command;
+        self.chosen.insert(slot, command.clone());
+
+        let (id, value) = match command {
+            Command::Request(id, value) => (id, value),
+            Command::Noop => return vec![],
+        };
+        self.answered.insert(id, (slot, value.clone()));
+
+        // Requests recovered from a previous leader were not necessarily
+        // requested from this proposer.
+        let i = match self.outstanding.iter().position(|(_, i, _)| *i == id) {
+            Some(i) => i,
+            None => return vec![],
+        };
+        let (client, _, _) = self.outstanding.remove(i);
+
+        vec![self.send(client, Body::Response(id, slot, value), now)]
+    }
+
+    fn process_renewed(&mut self, acceptor: Address, epoch: Epoch, sent_at: Instant) {
+        if epoch != self.epoch {
+            return;
+        }
+
+        let lease = match self.lease.as_mut() {
+            Some(lease) => lease,
+            None => return,
+        };
+        // Ignore grants of previous renewals, as their instant differs.
+        let granted = match lease.renewal.as_mut() {
+            Some((at, granted)) if *at == sent_at => granted,
+            _ => return,
+        };
+
+        granted.insert(acceptor);
+        // Like a value, a lease needs a phase-2 quorum, see
+        // `process_promise`.
+        if !self.quorums.is_phase2_quorum(&self.acceptors, granted) {
+            return;
+        }
+
+        lease.until = Some(sent_at + lease.duration);
+        lease.renewal = None;
+    }
+
+    fn heartbeat(&mut self, now: Instant) -> Vec<Msg<Body>> {
+        let due = match self.election.as_mut() {
+            Some(election) => election.heartbeat_due(now),
+            None => false,
+        };
+        if !due {
+            return vec![];
+        }
+
+        self.peers
+            .iter()
+            .map(|p| self.send(p.clone(), Body::Heartbeat, now))
+            .collect()
+    }
+
+    /// Renews the lease once half of it passed, as long as this proposer
+    /// leads.
+    fn renew_lease(&mut self, now: Instant) -> Vec<Msg<Body>> {
+        let leading = matches!(self.state, ProposerState::Leading { .. })
+            && *self.leader(now) == self.address;
+        let lease = match self.lease.as_mut() {
+            Some(lease) if leading => lease,
+            _ => return vec![],
+        };
+
+        let half = Instant(lease.duration.0 / 2);
+        if lease.until.map(|until| now + half < until).unwrap_or(false) {
+            return vec![];
+        }
+        // Give an ongoing renewal a chance to complete.
+        if let Some((at, _)) = lease.renewal {
+            if now < at + self.timeout.get() {
+                return vec![];
+            }
+        }
+
+        lease.renewal = Some((now, BTreeSet::new()));
+        self.broadcast_to_acceptors(Body::Renew(self.epoch, now), now)
+    }
+
+    fn prepare(&mut self, now: Instant) -> Vec<Msg<Body>> {
+        self.state = ProposerState::Preparing {
+            started_at: now,
+            last_progress_at: now,
+            promises: BTreeMap::new(),
+        };
+        if let Some(lease) = self.lease.as_mut() {
+            lease.expire();
+        }
+
+        self.broadcast_to_acceptors(Body::Prepare(self.epoch), now)
+    }
+
+    fn propose_queued(&mut self, now: Instant) -> Vec<Msg<Body>> {
+        let mut msgs = vec![];
+        while let Some((id, value)) = self.queue.pop_front() {
+            let slot = self.next_slot;
+            self.next_slot = Slot(slot.0 + 1);
+            msgs.append(&mut self.propose(slot, Command::Request(id, value), now));
+        }
+        msgs
+    }
+
+    fn propose(&mut self, slot: Slot, command: Command, now: Instant) -> Vec<Msg<Body>> {
+        if let ProposerState::Leading { last_progress_at } = &mut self.state {
+            *last_progress_at = now;
+        }
+
+        self.proposals.insert(
+            slot,
+            Proposal {
+                command: command.clone(),
+                accepts: BTreeSet::new(),
+                proposed_at: now,
+            },
+        );
+
+        self.broadcast_to_acceptors(Body::Propose(self.epoch, slot, command), now)
+    }
+
+    fn broadcast_to_acceptors(&self, b: Body, now: Instant) -> Vec<Msg<Body>> {
+        self.acceptors
+            .iter()
+            .map(|a| self.send(a.clone(), b.clone(), now))
+            .collect()
+    }
+
+    fn send(&self, to: Address, body: Body, now: Instant) -> Msg<Body> {
+        Msg {
+            header: Header {
+                from: self.address.clone(),
+                to,
+                at: now + 1,
+            },
+            body,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum ProposerState {
+    Idle,
+    /// Promises received so far, each with the accepted commands of all
+    /// slots.
+    Preparing {
+        started_at: Instant,
+        last_progress_at: Instant,
+        promises: BTreeMap<Address, BTreeMap<Slot, (Epoch, Command)>>,
+    },
+    /// Phase 1 succeeded for the current epoch, thus proposing right away.
+    Leading {
+        last_progress_at: Instant,
+    },
+}
+
+#[derive(Clone, Debug)]
+struct Proposal {
+    command: Command,
+    accepts: BTreeSet<Address>,
+    proposed_at: Instant,
+}
+
+#[derive(Clone, Debug)]
+struct Lease {
+    duration: Instant,
+    /// Instant the lease held by this proposer expires at.
+    until: Option<Instant>,
+    /// Instant the ongoing renewal was sent at and the acceptors that granted
+    /// it so far.
+    renewal: Option<(Instant, BTreeSet<Address>)>,
+}
+
+impl Lease {
+    fn expire(&mut self) {
+        self.until = None;
+        self.renewal = None;
+    }
+}
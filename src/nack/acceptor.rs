@@ -1,13 +1,19 @@
 use super::Body;
-use crate::{Address, Epoch, Header, Instant, Msg, Node, Value};
-use std::collections::VecDeque;
+use crate::storage::{AcceptorState, MemoryStorage, Storage};
+use crate::{Address, Header, Instant, Msg, Node, Slot, Timer};
+use std::collections::{BTreeMap, VecDeque};
 
-#[derive(Default, Debug)]
+#[derive(Clone, Debug)]
 pub struct Acceptor {
     address: Address,
-    promised_epoch: Option<Epoch>,
-    accepted: Option<(Epoch, Value)>,
+    state: AcceptorState,
+    storage: Box<dyn Storage>,
+    /// Whether to reply to proposers before the state is durable. Unsafe,
+    /// only useful to demonstrate why persisting first matters.
+    reply_before_persisting: bool,
     inbox: VecDeque<Msg<Body>>,
+    /// Replies waiting for the state they depend on to be durable.
+    outbox: Vec<(Instant, Msg<Body>)>,
 }
 
 impl Node<Body> for Acceptor {
@@ -18,37 +24,84 @@ impl Node<Body> for Acceptor {
     fn process(&mut self, now: Instant) -> Vec<Msg<Body>> {
         self.process(now)
     }
+
+    fn crash(&mut self, now: Instant) {
+        self.inbox.clear();
+        self.outbox.clear();
+        self.storage.crash(now);
+        self.state = self.storage.read().expect("to read persisted state");
+    }
+
+    fn timer(&self) -> Timer {
+        // Replies held back until the state they depend on is durable.
+        match self.outbox.iter().map(|(durable_at, _)| *durable_at).min() {
+            Some(at) => Timer::At(at),
+            None => Timer::Idle,
+        }
+    }
 }
 
-impl crate::Acceptor<Body> for Acceptor {}
+impl crate::Acceptor<Body> for Acceptor {
+    fn states(&self) -> BTreeMap<Slot, AcceptorState> {
+        std::iter::once((Slot(0), self.state.clone())).collect()
+    }
+
+    fn amnesia(&mut self, now: Instant) {
+        self.crash(now);
+        self.storage.wipe().expect("to wipe persisted state");
+        self.state = AcceptorState::default();
+    }
+}
 
 impl Acceptor {
     pub fn new(address: Address) -> Self {
+        Acceptor::with_storage(address, Box::new(MemoryStorage::default()))
+    }
+
+    pub fn with_storage(address: Address, storage: Box<dyn Storage>) -> Self {
         Acceptor {
             address,
-            promised_epoch: None,
-            accepted: None,
+            state: storage.read().expect("to read persisted state"),
+            storage,
+            reply_before_persisting: false,
             inbox: VecDeque::new(),
+            outbox: vec![],
         }
     }
 
+    /// Reply to proposers without waiting for the state to be durable. This
+    /// violates safety in the face of crashes.
+    pub fn reply_before_persisting(mut self) -> Self {
+        self.reply_before_persisting = true;
+        self
+    }
+
     pub fn address(&self) -> Address {
         self.address.clone()
     }
 
     pub fn process(&mut self, now: Instant) -> Vec<Msg<Body>> {
+        let (durable, outbox): (Vec<_>, Vec<_>) = self
+            .outbox
+            .drain(0..)
+            .partition(|(durable_at, _)| *durable_at <= now);
+        self.outbox = outbox;
+
         let messages: Vec<Msg<Body>> = self.inbox.drain(0..).collect();
-        messages
+        durable
             .into_iter()
-            .map(|m| self.process_msg(m, now))
-            .flatten()
+            .map(|(_, mut m)| {
+                m.header.at = now + 1;
+                m
+            })
+            .chain(messages.into_iter().flat_map(|m| self.process_msg(m, now)))
             .collect()
     }
 
     fn process_msg(&mut self, m: Msg<Body>, now: Instant) -> Vec<Msg<Body>> {
         match m.body {
             Body::Prepare(i) => {
-                if let Some(e) = self.promised_epoch {
+                if let Some(e) = self.state.promised_epoch {
                     if e > i {
                         return vec![Msg {
                             header: Header {
@@ -61,19 +114,14 @@ impl Acceptor {
                     }
                 }
 
-                self.promised_epoch = Some(i);
+                let mut state = self.state.clone();
+                state.promised_epoch = Some(i);
+                let body = Body::Promise(i, state.accepted.clone());
 
-                return vec![Msg {
-                    header: Header {
-                        from: self.address.clone(),
-                        to: m.header.from,
-                        at: now + 1,
-                    },
-                    body: Body::Promise(i, self.accepted.clone()),
-                }];
+                self.persist_and_reply(state, m.header.from, body, now)
             }
             Body::Propose(proposed_epoch, value) => {
-                if let Some(e) = self.promised_epoch {
+                if let Some(e) = self.state.promised_epoch {
                     if e > proposed_epoch {
                         return vec![Msg {
                             header: Header {
@@ -86,18 +134,47 @@ impl Acceptor {
                     }
                 }
 
-                self.accepted = Some((proposed_epoch, value));
+                // Accepting implies promising, otherwise a proposal of a
+                // lower epoch could still replace the accepted value.
+                let mut state = self.state.clone();
+                state.promised_epoch = Some(proposed_epoch);
+                state.accepted = Some((proposed_epoch, value));
 
-                return vec![Msg {
-                    header: Header {
-                        from: self.address.clone(),
-                        to: m.header.from,
-                        at: now + 1,
-                    },
-                    body: Body::Accept(proposed_epoch),
-                }];
+                self.persist_and_reply(state, m.header.from, Body::Accept(proposed_epoch), now)
             }
             _ => unimplemented!(),
         }
     }
+
+    /// Persist the given state and reply to the proposer once it is durable.
+    /// Failing to persist the state results in no reply at all.
+    fn persist_and_reply(
+        &mut self,
+        state: AcceptorState,
+        to: Address,
+        body: Body,
+        now: Instant,
+    ) -> Vec<Msg<Body>> {
+        let durable_at = match self.storage.write(&state, now) {
+            Ok(durable_at) => durable_at,
+            Err(_) => return vec![],
+        };
+        self.state = state;
+
+        let reply = Msg {
+            header: Header {
+                from: self.address.clone(),
+                to,
+                at: now + 1,
+            },
+            body,
+        };
+
+        if durable_at <= now || self.reply_before_persisting {
+            return vec![reply];
+        }
+
+        self.outbox.push((durable_at, reply));
+        vec![]
+    }
 }
@@ -1,17 +1,41 @@
-use super::Body;
-use crate::{Address, Epoch, Header, Instant, Msg, Node, Value};
-use std::collections::VecDeque;
-
-const TIMEOUT: Instant = Instant(10);
+use super::{Body, Jitter, NackPolicy, RetryPolicy};
+use crate::quorum::QuorumSystem;
+use crate::timeout::{ProposerConfig, Timeout};
+use crate::{Address, Epoch, Header, Instant, Msg, Node, RequestId, Timer, Value};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 
 /// A sequential proposer, handling a single request at a time.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Proposer {
     address: Address,
     pub acceptors: Vec<Address>,
     inbox: VecDeque<Msg<Body>>,
     epoch: Epoch,
     state: ProposerState,
+    /// Requests waiting for the one being handled, oldest first.
+    queue: VecDeque<(Address, RequestId, Value)>,
+    /// Number of requests the queue holds at most, see
+    /// [`Proposer::with_queue_capacity`].
+    queue_capacity: usize,
+    /// Values decided for the requests responded to so far, answering a
+    /// resubmitted request right away.
+    answered: BTreeMap<RequestId, Value>,
+    quorums: Box<dyn QuorumSystem>,
+    retry_policy: RetryPolicy,
+    jitter: Jitter,
+    /// Consecutive nacks received while handling the current request.
+    nacks: u32,
+    /// Instant to retry the current request at after a nack, see
+    /// [`Proposer::with_retry_policy`].
+    retry_at: Option<Instant>,
+    nack_policy: NackPolicy,
+    /// Nacks received for the current epoch, see
+    /// [`Proposer::with_nack_policy`].
+    nacked: Option<NackTally>,
+    timeout: Timeout,
+    /// Instant the messages of the current phase were sent at, measuring the
+    /// round trip of each reply.
+    sent_at: Instant,
 }
 
 impl Node<Body> for Proposer {
@@ -22,9 +46,56 @@ impl Node<Body> for Proposer {
     fn process(&mut self, now: Instant) -> Vec<Msg<Body>> {
         self.process(now)
     }
+
+    fn crash(&mut self, _now: Instant) {
+        self.inbox.clear();
+        self.queue.clear();
+        self.state = ProposerState::Idle;
+        self.nacks = 0;
+        self.retry_at = None;
+        self.nacked = None;
+        // Round trips observed before the crash are forgotten.
+        self.timeout.restart();
+        // A request resubmitted after the crash goes through another round,
+        // deciding the same value anyway.
+        self.answered.clear();
+        // The epoch is persisted. Moving on to the next one prevents reusing
+        // an epoch for a different value after the crash.
+        self.epoch = Epoch::new(self.epoch.epoch + 1, self.epoch.identifier);
+    }
+
+    fn timer(&self) -> Timer {
+        // Not timing out while backing off.
+        if let Some(at) = self.retry_at {
+            return Timer::At(at);
+        }
+
+        let timeout = match self.state.last_progress_at() {
+            Some(t) => Timer::At(t + self.timeout.get()),
+            None => Timer::Idle,
+        };
+        match (self.nack_policy, &self.nacked) {
+            (NackPolicy::QuorumOrTimeout(after), Some(tally)) if tally.epoch == self.epoch => {
+                timeout.min(Timer::At(tally.first_at + after))
+            }
+            _ => timeout,
+        }
+    }
 }
 
-impl crate::Proposer<Body> for Proposer {}
+impl crate::Proposer<Body> for Proposer {
+    fn set_quorums(&mut self, quorums: Box<dyn QuorumSystem>) {
+        self.quorums = quorums;
+    }
+
+    fn set_config(&mut self, config: ProposerConfig) {
+        self.timeout.set_config(config);
+    }
+
+    fn config(&self) -> ProposerConfig {
+        self.timeout.config()
+    }
+}
 
 impl Proposer {
     pub fn new(address: Address, initial_epoch: Epoch, acceptors: Vec<Address>) -> Self {
@@ -34,61 +105,146 @@ impl Proposer {
             inbox: Default::default(),
             epoch: initial_epoch,
             state: ProposerState::Idle,
+            queue: VecDeque::new(),
+            queue_capacity: usize::MAX,
+            answered: BTreeMap::new(),
+            quorums: Default::default(),
+            retry_policy: RetryPolicy::default(),
+            jitter: Jitter::new(initial_epoch.identifier as u64),
+            nacks: 0,
+            retry_at: None,
+            nack_policy: NackPolicy::default(),
+            nacked: None,
+            timeout: Timeout::new(ProposerConfig::default(), initial_epoch.identifier as u64),
+            sent_at: Instant::default(),
         }
     }
 
+    /// Queues at most the given number of requests while handling another
+    /// one, rejecting further requests with [`Body::Busy`]. Unbounded by
+    /// default.
+    pub fn with_queue_capacity(mut self, capacity: usize) -> Self {
+        self.queue_capacity = capacity;
+        self
+    }
+
+    /// Uses the given timeouts instead of the default fixed one.
+    pub fn with_config(mut self, config: ProposerConfig) -> Self {
+        self.timeout.set_config(config);
+        self
+    }
+
+    /// Waits according to the given policy after a nack before retrying,
+    /// retrying right away by default. Random delays are seeded with the
+    /// identifier of the initial epoch, thus differing between proposers.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Gives up on the current epoch after nacks according to the given
+    /// policy, on the first nack by default.
+    pub fn with_nack_policy(mut self, policy: NackPolicy) -> Self {
+        self.nack_policy = policy;
+        self
+    }
+
     fn process(&mut self, now: Instant) -> Vec<Msg<Body>> {
         let messages: Vec<Msg<Body>> = self.inbox.drain(0..).collect();
-        let responses: Vec<Msg<Body>> = messages
+        let mut responses: Vec<Msg<Body>> = messages
             .into_iter()
-            .map(|m| self.process_msg(m, now))
-            .flatten()
+            .flat_map(|m| self.process_msg(m, now))
             .collect();
+
+        // Give up on the current epoch if nacked too long ago.
+        if let (NackPolicy::QuorumOrTimeout(timeout), Some(tally)) =
+            (self.nack_policy, &self.nacked)
+        {
+            if tally.epoch == self.epoch && tally.first_at + timeout <= now {
+                responses.append(&mut self.back_off(now));
+            }
+        }
+
+        // Retry once backed off after a nack.
+        if self.retry_at.map(|at| at <= now).unwrap_or(false) {
+            self.retry_at = None;
+            responses.append(&mut self.retry(now));
+        }
+
         if !responses.is_empty() {
             // We made progress, thus returning.
             return responses;
         }
 
-        // Check whether we are still within the timeout.
-        if self
-            .state
-            .last_progress_at()
-            .map(|t| now - t < TIMEOUT)
-            .unwrap_or(true)
+        // Check whether we are still within the timeout, not timing out while
+        // backing off.
+        if self.retry_at.is_some()
+            || self
+                .state
+                .last_progress_at()
+                .map(|t| !self.timeout.is_expired(t, now))
+                .unwrap_or(true)
         {
             return vec![];
         }
 
         // We timed out - going back to preparing.
+        self.timeout.expire();
         self.epoch = Epoch::new(self.epoch.epoch + 1, self.epoch.identifier);
         self.retry(now)
     }
 
     fn process_msg(&mut self, m: Msg<Body>, now: Instant) -> Vec<Msg<Body>> {
         match m.body {
-            Body::Request(v) => self.process_request(m.header, v, now),
+            Body::Request(id, v) => self.process_request(m.header.from, id, v, now),
             Body::Promise(promised_epoch, accepted) => {
-                self.process_promise(promised_epoch, accepted, now)
+                self.process_promise(m.header.from, promised_epoch, accepted, now)
+            }
+            Body::Accept(epoch) => self.process_accept(m.header.from, epoch, now),
+            Body::Nack(e, f) => self.process_nack(m.header.from, e, f, now),
+            Body::Prepare(_) | Body::Propose(_, _) | Body::Response(_, _) | Body::Busy(_) => {
+                unreachable!()
             }
-            Body::Accept(epoch) => self.process_accept(epoch, now),
-            Body::Nack(e, f) => self.process_nack(e, f, now),
-            Body::Prepare(_) | Body::Propose(_, _) | Body::Response(_) => unreachable!(),
         }
     }
 
-    fn process_request(&mut self, header: Header, value: Value, now: Instant) -> Vec<Msg<Body>> {
+    fn process_request(
+        &mut self,
+        client: Address,
+        id: RequestId,
+        value: Value,
+        now: Instant,
+    ) -> Vec<Msg<Body>> {
+        // A resubmitted request is answered right away once decided, and
+        // dropped while still being handled or queued.
+        if let Some(decided) = self.answered.get(&id) {
+            return vec![self.respond(client, id, decided.clone(), now)];
+        }
+        if self
+            .state
+            .request()
+            .map(|(_, i, _)| i == id)
+            .unwrap_or(false)
+            || self.queue.iter().any(|(_, i, _)| *i == id)
+        {
+            return vec![];
+        }
+
         match self.state {
             ProposerState::Unreachable => unreachable!(),
             ProposerState::Preparing { .. } | ProposerState::Proposing { .. } => {
-                self.inbox.push_back(Msg {
-                    header,
-                    body: Body::Request(value),
-                });
-                return vec![];
+                if self.queue.len() >= self.queue_capacity {
+                    return vec![self.send(client, Body::Busy(id), now)];
+                }
+
+                self.queue.push_back((client, id, value));
+                vec![]
             }
             ProposerState::Idle => {
                 self.state = ProposerState::Preparing {
                     last_progress_at: now,
+                    client,
+                    id,
                     value,
                     promises: vec![],
                 };
@@ -102,6 +258,7 @@ impl Proposer {
 
     fn process_promise(
         &mut self,
+        acceptor: Address,
         promised_epoch: Epoch,
         accepted: Option<(Epoch, Value)>,
         now: Instant,
@@ -116,21 +273,37 @@ impl Proposer {
             ProposerState::Unreachable => unreachable!(),
             ProposerState::Idle | ProposerState::Proposing { .. } => {
                 self.state = state;
-                return vec![];
+                vec![]
             }
             ProposerState::Preparing {
+                client,
+                id,
                 value,
                 mut promises,
                 ..
             } => {
-                promises.push(Promise {
-                    epoch: promised_epoch,
-                    accepted,
-                });
+                // Ignore duplicate promises, e.g. due to a faulty network.
+                if promises.iter().any(|p| p.acceptor == acceptor) {
+                    self.state = ProposerState::Preparing {
+                        last_progress_at: now,
+                        client,
+                        id,
+                        value,
+                        promises,
+                    };
+                    return vec![];
+                }
+
+                self.timeout.observe(now - self.sent_at);
+                promises.push(Promise { acceptor, accepted });
 
-                if promises.len() < self.acceptors.len() / 2 + 1 {
+                let promised: BTreeSet<Address> =
+                    promises.iter().map(|p| p.acceptor.clone()).collect();
+                if !self.quorums.is_phase1_quorum(&self.acceptors, &promised) {
                     self.state = ProposerState::Preparing {
                         last_progress_at: now,
+                        client,
+                        id,
                         value,
                         promises,
                     };
@@ -154,20 +327,23 @@ impl Proposer {
                             None => accepted,
                         });
 
+                let value = highest_accepted.map(|a| a.1).unwrap_or(value);
                 self.state = ProposerState::Proposing {
                     last_progress_at: now,
-                    value: highest_accepted.map(|a| a.1).unwrap_or(value),
-                    received_accepts: 0,
+                    client,
+                    id,
+                    value: value.clone(),
+                    received_accepts: BTreeSet::new(),
                 };
 
-                let propose_body = Body::Propose(self.epoch, self.state.value().unwrap());
+                let propose_body = Body::Propose(self.epoch, value);
 
                 self.broadcast_to_acceptors(propose_body, now)
             }
         }
     }
 
-    fn process_accept(&mut self, epoch: Epoch, now: Instant) -> Vec<Msg<Body>> {
+    fn process_accept(&mut self, acceptor: Address, epoch: Epoch, now: Instant) -> Vec<Msg<Body>> {
         // Ignore any messages outside our current epoch.
         if epoch != self.epoch {
             return vec![];
@@ -178,17 +354,28 @@ impl Proposer {
             ProposerState::Unreachable => unreachable!(),
             ProposerState::Idle | ProposerState::Preparing { .. } => {
                 self.state = state;
-                return vec![];
+                vec![]
             }
             ProposerState::Proposing {
+                client,
+                id,
                 value,
-                received_accepts,
+                mut received_accepts,
                 ..
             } => {
-                let received_accepts = received_accepts + 1;
+                // Duplicate accepts, e.g. due to a faulty network, are
+                // deduplicated by the set.
+                if received_accepts.insert(acceptor) {
+                    self.timeout.observe(now - self.sent_at);
+                }
 
-                if received_accepts < self.acceptors.len() / 2 + 1 {
+                if !self
+                    .quorums
+                    .is_phase2_quorum(&self.acceptors, &received_accepts)
+                {
                     self.state = ProposerState::Proposing {
+                        client,
+                        id,
                         value,
                         received_accepts,
                         last_progress_at: now,
@@ -198,24 +385,21 @@ impl Proposer {
 
                 self.epoch = Epoch::new(self.epoch.epoch + 1, self.epoch.identifier);
                 self.state = ProposerState::Idle;
+                self.nacks = 0;
+                self.nacked = None;
+
+                self.answered.insert(id, value.clone());
 
-                return vec![Msg {
-                    header: Header {
-                        from: self.address.clone(),
-                        // TODO: We need to track the client address along the way.
-                        to: Address::new(""),
-                        at: now + 1,
-                    },
-                    body: Body::Response(value),
-                }];
+                let mut msgs = vec![self.respond(client, id, value, now)];
+                msgs.append(&mut self.dequeue(now));
+                msgs
             }
         }
     }
 
-    // TODO: Do we need to retry always, or only if we got a majority of nacks
-    // back?
     fn process_nack(
         &mut self,
+        acceptor: Address,
         our_epoch: Epoch,
         higher_epoch: Epoch,
         now: Instant,
@@ -225,25 +409,88 @@ impl Proposer {
             return vec![];
         }
 
-        let state = std::mem::replace(&mut self.state, ProposerState::Unreachable);
-        match state {
+        let quorum = match self.state {
             ProposerState::Unreachable => unreachable!(),
-            ProposerState::Idle => {
-                self.state = state;
-                vec![]
-            }
-            ProposerState::Preparing { .. } | ProposerState::Proposing {..}  => {
-                // self.retry() extracts the value from the current scope, but
-                // we replaced it above with ProposerState::Unreachable. Make
-                // sure to switch it back.
-                self.state = state;
-                self.epoch = Epoch::new(higher_epoch.epoch + 1, self.epoch.identifier);
-                self.retry(now)
+            ProposerState::Idle => return vec![],
+            ProposerState::Preparing { .. } | ProposerState::Proposing { .. } => {
+                let epoch = self.epoch;
+                let tally = self
+                    .nacked
+                    .get_or_insert_with(|| NackTally::new(epoch, higher_epoch, now));
+                if tally.epoch != epoch {
+                    *tally = NackTally::new(epoch, higher_epoch, now);
+                }
+                tally.acceptors.insert(acceptor);
+                tally.highest = tally.highest.max(higher_epoch);
+
+                if let ProposerState::Preparing { .. } = self.state {
+                    self.quorums
+                        .is_phase1_quorum(&self.acceptors, &tally.acceptors)
+                } else {
+                    self.quorums
+                        .is_phase2_quorum(&self.acceptors, &tally.acceptors)
+                }
             }
+        };
+
+        // Otherwise waiting for further nacks, a quorum of promises or
+        // accepts, or a timeout.
+        if quorum || self.nack_policy == NackPolicy::First {
+            return self.back_off(now);
+        }
+        vec![]
+    }
+
+    /// Gives up on the current epoch after being nacked, retrying with an
+    /// epoch above the highest one nacked with once the retry policy allows.
+    fn back_off(&mut self, now: Instant) -> Vec<Msg<Body>> {
+        let highest = match self.nacked.take() {
+            Some(tally) => tally.highest,
+            None => return vec![],
+        };
+        self.epoch = Epoch::new(highest.epoch + 1, self.epoch.identifier);
+        self.nacks += 1;
+
+        // Replies in the previous epoch are ignored from now on, thus
+        // waiting for nothing but the delay.
+        let delay = self.retry_policy.delay(self.nacks, &mut self.jitter);
+        if delay == Instant(0) {
+            return self.retry(now);
+        }
+        self.retry_at = Some(now + delay);
+        vec![]
+    }
+
+    /// Starts handling the oldest queued request, if idle.
+    fn dequeue(&mut self, now: Instant) -> Vec<Msg<Body>> {
+        let mut msgs = vec![];
+        while matches!(self.state, ProposerState::Idle) {
+            let (client, id, value) = match self.queue.pop_front() {
+                Some(r) => r,
+                None => break,
+            };
+            msgs.append(&mut self.process_request(client, id, value, now));
+        }
+        msgs
+    }
+
+    fn respond(&self, client: Address, id: RequestId, value: Value, now: Instant) -> Msg<Body> {
+        self.send(client, Body::Response(id, value), now)
+    }
+
+    fn send(&self, to: Address, body: Body, now: Instant) -> Msg<Body> {
+        Msg {
+            header: Header {
+                from: self.address.clone(),
+                to,
+                at: now + 1,
+            },
+            body,
         }
     }
 
     fn broadcast_to_acceptors(&mut self, b: Body, now: Instant) -> Vec<Msg<Body>> {
+        self.sent_at = now;
         self.acceptors
             .iter()
             .map(|a| Msg {
@@ -260,13 +507,15 @@ impl Proposer {
     /// Try to serve the client request by starting all over with a Prepare.
     /// This is necessary on a timeout or e.g. a retry.
     fn retry(&mut self, now: Instant) -> Vec<Msg<Body>> {
-        let value = self
+        let (client, id, value) = self
             .state
-            .value()
-            .expect("can't be reached from idle state, thus there is a value");
+            .request()
+            .expect("can't be reached from idle state, thus there is a request");
 
         self.state = ProposerState::Preparing {
             last_progress_at: now,
+            client,
+            id,
             value,
             promises: vec![],
         };
@@ -280,16 +529,21 @@ impl Proposer {
 #[derive(Clone, Debug)]
 enum ProposerState {
     Idle,
-    /// Epoch and value to propose and promises received so far.
+    /// Client and value to propose on behalf of and promises received so
+    /// far.
     Preparing {
         last_progress_at: Instant,
+        client: Address,
+        id: RequestId,
         value: Value,
         promises: Vec<Promise>,
     },
     Proposing {
         last_progress_at: Instant,
+        client: Address,
+        id: RequestId,
         value: Value,
-        received_accepts: usize,
+        received_accepts: BTreeSet<Address>,
     },
     Unreachable,
 }
@@ -308,18 +562,45 @@ impl ProposerState {
         }
     }
 
-    fn value(&self) -> Option<Value> {
+    /// Returns the client, the identifier and the value of the request being
+    /// handled.
+    fn request(&self) -> Option<(Address, RequestId, Value)> {
         match self {
             ProposerState::Unreachable => unreachable!(),
             ProposerState::Idle => None,
-            ProposerState::Preparing { value, .. } => Some(value.clone()),
-            ProposerState::Proposing { value, .. } => Some(value.clone()),
+            ProposerState::Preparing {
+                client, id, value, ..
+            }
+            | ProposerState::Proposing {
+                client, id, value, ..
+            } => Some((client.clone(), *id, value.clone())),
         }
     }
 }
 
 #[derive(Clone, Debug)]
 struct Promise {
-    epoch: Epoch,
+    acceptor: Address,
     accepted: Option<(Epoch, Value)>,
 }
+
+/// Acceptors having nacked an epoch of a proposer, the highest epoch they
+/// nacked it with and the instant of the first nack.
+#[derive(Clone, Debug)]
+struct NackTally {
+    epoch: Epoch,
+    acceptors: BTreeSet<Address>,
+    highest: Epoch,
+    first_at: Instant,
+}
+
+impl NackTally {
+    fn new(epoch: Epoch, highest: Epoch, first_at: Instant) -> Self {
+        Self {
+            epoch,
+            acceptors: BTreeSet::new(),
+            highest,
+            first_at,
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use crate::timeout::Jitter;
+use crate::Instant;
+
+/// How long a [`super::Proposer`] waits after a nack before retrying with a
+/// higher epoch.
+///
+/// Retrying right away lets two proposers nack each other forever, each
+/// preparing a higher epoch before the other one gets to propose. Waiting
+/// gives one of them the chance to get its value chosen.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RetryPolicy {
+    /// Retry right away, the default.
+    #[default]
+    Immediate,
+    /// Wait the same duration before each retry.
+    Fixed(Instant),
+    /// Wait up to `base` doubled with each consecutive nack, capped at `max`.
+    /// The actual duration is picked at random below that bound, keeping
+    /// proposers from retrying in lockstep.
+    Exponential { base: Instant, max: Instant },
+}
+
+impl RetryPolicy {
+    /// Returns the duration to wait before the retry following the given
+    /// number of consecutive nacks, the first nack being 1.
+    pub fn delay(&self, nacks: u32, jitter: &mut Jitter) -> Instant {
+        match self {
+            RetryPolicy::Immediate => Instant(0),
+            RetryPolicy::Fixed(delay) => *delay,
+            RetryPolicy::Exponential { base, max } => {
+                let doublings = nacks.saturating_sub(1).min(63);
+                let bound = base.0.saturating_mul(1u64 << doublings).min(max.0);
+                Instant(jitter.below(bound.saturating_add(1)))
+            }
+        }
+    }
+}
+
+/// When a [`super::Proposer`] gives up on its epoch after being nacked,
+/// retrying with a higher one according to its [`RetryPolicy`]. Without
+/// giving up, it still retries on its regular timeout.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NackPolicy {
+    /// Retry on the first nack, the default.
+    #[default]
+    First,
+    /// Retry once a quorum of acceptors nacked the current epoch, for the
+    /// phase the proposer is in. A single nack doesn't rule out reaching a
+    /// quorum with the other acceptors.
+    Quorum,
+    /// Like [`NackPolicy::Quorum`], yet retry at the latest the given
+    /// duration after the first nack of the current epoch.
+    QuorumOrTimeout(Instant),
+}
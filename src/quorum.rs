@@ -0,0 +1,277 @@
+//! Quorum systems deciding which sets of acceptors a proposer needs to hear
+//! from in each of the two phases of Paxos.
+//!
+//! Classic Paxos uses a majority of acceptors for both phases. As observed by
+//! Flexible Paxos, it suffices for every phase-1 quorum to intersect every
+//! phase-2 quorum. Phase-1 quorums need not intersect each other, neither do
+//! phase-2 quorums. E.g. a smaller phase-2 quorum lowers the latency of the
+//! common case at the cost of availability when recovering through phase 1.
+//! See [`verify`] to check a quorum system before relying on it.
+
+use crate::Address;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Decides whether a set of acceptors forms a quorum of either phase.
+///
+/// Each check is handed all acceptors of the cluster along with the ones
+/// that responded, the latter being a subset of the former.
+pub trait QuorumSystem: std::fmt::Debug {
+    /// Returns whether promises by the given acceptors suffice to go on with
+    /// phase 2.
+    fn is_phase1_quorum(&self, acceptors: &[Address], responded: &BTreeSet<Address>) -> bool;
+    /// Returns whether a value accepted by the given acceptors is chosen.
+    fn is_phase2_quorum(&self, acceptors: &[Address], responded: &BTreeSet<Address>) -> bool;
+    /// Returns a copy of the quorum system, e.g. to hand it to each proposer.
+    fn box_clone(&self) -> Box<dyn QuorumSystem>;
+
+    /// Checks the quorum system against the given acceptors without
+    /// enumerating their subsets, see [`verify`]. `None` if the quorum
+    /// system offers no such shortcut.
+    fn verify_analytically(&self, _acceptors: &[Address]) -> Option<Result<(), String>> {
+        None
+    }
+}
+
+impl Clone for Box<dyn QuorumSystem> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+impl Default for Box<dyn QuorumSystem> {
+    fn default() -> Self {
+        Box::new(Quorums::default())
+    }
+}
+
+/// Quorums made of any acceptors as long as there are enough of them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Quorums {
+    /// A majority of acceptors in both phases.
+    #[default]
+    Majority,
+    /// Fixed sizes for each phase. The two sizes need to add up to more than
+    /// the number of acceptors.
+    Flexible { phase1: usize, phase2: usize },
+}
+
+impl Quorums {
+    /// Returns the number of promises needed out of the given number of
+    /// acceptors.
+    pub fn phase1(&self, acceptors: usize) -> usize {
+        match self {
+            Quorums::Majority => acceptors / 2 + 1,
+            Quorums::Flexible { phase1, .. } => *phase1,
+        }
+    }
+
+    /// Returns the number of accepts needed out of the given number of
+    /// acceptors for a value to be chosen.
+    pub fn phase2(&self, acceptors: usize) -> usize {
+        match self {
+            Quorums::Majority => acceptors / 2 + 1,
+            Quorums::Flexible { phase2, .. } => *phase2,
+        }
+    }
+}
+
+impl QuorumSystem for Quorums {
+    fn is_phase1_quorum(&self, acceptors: &[Address], responded: &BTreeSet<Address>) -> bool {
+        responded.len() >= self.phase1(acceptors.len())
+    }
+
+    fn is_phase2_quorum(&self, acceptors: &[Address], responded: &BTreeSet<Address>) -> bool {
+        responded.len() >= self.phase2(acceptors.len())
+    }
+
+    fn box_clone(&self) -> Box<dyn QuorumSystem> {
+        Box::new(*self)
+    }
+
+    /// Any two quorums of the given sizes intersect if and only if the sizes
+    /// add up to more than the number of acceptors.
+    fn verify_analytically(&self, acceptors: &[Address]) -> Option<Result<(), String>> {
+        let n = acceptors.len();
+        let (phase1, phase2) = (self.phase1(n), self.phase2(n));
+        Some(if phase1 > n {
+            Err(format!("{:?} has no phase-1 quorum", self))
+        } else if phase2 > n {
+            Err(format!("{:?} has no phase-2 quorum", self))
+        } else if phase1 + phase2 <= n {
+            Err(format!(
+                "{:?} has disjoint quorums of {} acceptors in phase 1 and {} in phase 2",
+                self, phase1, phase2
+            ))
+        } else {
+            Ok(())
+        })
+    }
+}
+
+/// Weighted voting, with a quorum in either phase holding more than half of
+/// the total weight. Acceptors without a weight do not count.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Weighted {
+    pub weights: BTreeMap<Address, u64>,
+}
+
+impl Weighted {
+    fn is_quorum(&self, responded: &BTreeSet<Address>) -> bool {
+        let total: u64 = self.weights.values().sum();
+        let votes: u64 = responded.iter().filter_map(|a| self.weights.get(a)).sum();
+        votes * 2 > total
+    }
+}
+
+impl QuorumSystem for Weighted {
+    fn is_phase1_quorum(&self, _acceptors: &[Address], responded: &BTreeSet<Address>) -> bool {
+        self.is_quorum(responded)
+    }
+
+    fn is_phase2_quorum(&self, _acceptors: &[Address], responded: &BTreeSet<Address>) -> bool {
+        self.is_quorum(responded)
+    }
+
+    fn box_clone(&self) -> Box<dyn QuorumSystem> {
+        Box::new(self.clone())
+    }
+}
+
+/// Acceptors arranged in a grid of equally long rows. A phase-1 quorum is
+/// any full column, a phase-2 quorum any full row, each row intersecting
+/// each column. Phase 2 thus only involves a single row.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Grid {
+    pub rows: Vec<Vec<Address>>,
+}
+
+impl QuorumSystem for Grid {
+    fn is_phase1_quorum(&self, _acceptors: &[Address], responded: &BTreeSet<Address>) -> bool {
+        let columns = self.rows.first().map(|r| r.len()).unwrap_or(0);
+        (0..columns).any(|c| {
+            self.rows
+                .iter()
+                .all(|r| r.get(c).map(|a| responded.contains(a)).unwrap_or(false))
+        })
+    }
+
+    fn is_phase2_quorum(&self, _acceptors: &[Address], responded: &BTreeSet<Address>) -> bool {
+        self.rows
+            .iter()
+            .any(|r| !r.is_empty() && r.iter().all(|a| responded.contains(a)))
+    }
+
+    fn box_clone(&self) -> Box<dyn QuorumSystem> {
+        Box::new(self.clone())
+    }
+}
+
+/// Acceptors partitioned into groups, e.g. one per datacenter. A quorum in
+/// either phase is a majority of the members of each of a majority of the
+/// groups.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hierarchical {
+    pub groups: Vec<Vec<Address>>,
+}
+
+impl Hierarchical {
+    fn is_quorum(&self, responded: &BTreeSet<Address>) -> bool {
+        let groups = self
+            .groups
+            .iter()
+            .filter(|g| {
+                let members = g.iter().filter(|a| responded.contains(a)).count();
+                members > g.len() / 2
+            })
+            .count();
+        groups > self.groups.len() / 2
+    }
+}
+
+impl QuorumSystem for Hierarchical {
+    fn is_phase1_quorum(&self, _acceptors: &[Address], responded: &BTreeSet<Address>) -> bool {
+        self.is_quorum(responded)
+    }
+
+    fn is_phase2_quorum(&self, _acceptors: &[Address], responded: &BTreeSet<Address>) -> bool {
+        self.is_quorum(responded)
+    }
+
+    fn box_clone(&self) -> Box<dyn QuorumSystem> {
+        Box::new(self.clone())
+    }
+}
+
+/// Largest number of acceptors [`verify`] enumerates the subsets of.
+pub const MAX_VERIFIED_ACCEPTORS: usize = 16;
+
+/// Returns whether [`verify`] can check the given quorum system against the
+/// given number of acceptors.
+pub fn is_verifiable(system: &dyn QuorumSystem, acceptors: &[Address]) -> bool {
+    acceptors.len() <= MAX_VERIFIED_ACCEPTORS || system.verify_analytically(acceptors).is_some()
+}
+
+/// Checks the given quorum system against the given acceptors, see
+/// [`QuorumSystem::verify_analytically`], otherwise by enumerating all
+/// subsets of them. Returns an error describing the first problem found: a
+/// phase-1 and a phase-2 quorum that do not intersect, or a phase that has no
+/// quorum even with all acceptors responding.
+///
+/// The number of checks when enumerating grows with 3^n, thus refusing more
+/// than 16 acceptors, see [`is_verifiable`].
+pub fn verify(system: &dyn QuorumSystem, acceptors: &[Address]) -> Result<(), String> {
+    if let Some(result) = system.verify_analytically(acceptors) {
+        return result;
+    }
+
+    let n = acceptors.len();
+    if n > MAX_VERIFIED_ACCEPTORS {
+        return Err(format!(
+            "can not verify {:?} with more than {} acceptors",
+            system, MAX_VERIFIED_ACCEPTORS
+        ));
+    }
+
+    let subset = |mask: usize| -> BTreeSet<Address> {
+        (0..n)
+            .filter(|i| mask & (1 << i) != 0)
+            .map(|i| acceptors[i].clone())
+            .collect()
+    };
+    let all = (1 << n) - 1;
+    let phase1: Vec<bool> = (0..=all)
+        .map(|m| system.is_phase1_quorum(acceptors, &subset(m)))
+        .collect();
+    let phase2: Vec<bool> = (0..=all)
+        .map(|m| system.is_phase2_quorum(acceptors, &subset(m)))
+        .collect();
+
+    if !phase1[all] {
+        return Err(format!("{:?} has no phase-1 quorum", system));
+    }
+    if !phase2[all] {
+        return Err(format!("{:?} has no phase-2 quorum", system));
+    }
+
+    for q1 in (0..=all).filter(|m| phase1[*m]) {
+        // Enumerates all subsets of the acceptors outside of `q1`.
+        let rest = all & !q1;
+        let mut q2 = rest;
+        loop {
+            if phase2[q2] {
+                return Err(format!(
+                    "{:?} has disjoint quorums {:?} in phase 1 and {:?} in phase 2",
+                    system,
+                    subset(q1),
+                    subset(q2)
+                ));
+            }
+            if q2 == 0 {
+                break;
+            }
+            q2 = (q2 - 1) & rest;
+        }
+    }
+
+    Ok(())
+}
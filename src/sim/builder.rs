@@ -0,0 +1,448 @@
+use super::{Client, Crash, Faults, Monitor, Network, NetworkModel, Partition, Simulator};
+use crate::quorum::QuorumSystem;
+use crate::timeout::ProposerConfig;
+use crate::{
+    Acceptor, Address, Body, Epoch, Header, Instant, Learner, Learning, Msg, Proposer, RequestId,
+    Value,
+};
+use std::collections::{BTreeMap, BTreeSet};
+use std::marker::PhantomData;
+
+/// Constructs proposers of a simulated cluster.
+///
+/// Implemented for any `Fn(Address, Epoch, Vec<Address>) -> P`, thus e.g.
+/// `classic::Proposer::new` can be used directly.
+pub trait ProposerFactory<B: Body> {
+    type Proposer: Proposer<B>;
+
+    /// Returns a new proposer with the given address, initial epoch and
+    /// acceptors to talk to.
+    fn new_proposer(
+        &self,
+        address: Address,
+        initial_epoch: Epoch,
+        acceptors: Vec<Address>,
+    ) -> Self::Proposer;
+}
+
+impl<B: Body, P: Proposer<B>, F: Fn(Address, Epoch, Vec<Address>) -> P> ProposerFactory<B> for F {
+    type Proposer = P;
+
+    fn new_proposer(&self, address: Address, initial_epoch: Epoch, acceptors: Vec<Address>) -> P {
+        self(address, initial_epoch, acceptors)
+    }
+}
+
+/// Constructs acceptors of a simulated cluster.
+///
+/// Implemented for any `Fn(Address) -> A`, thus e.g. `classic::Acceptor::new`
+/// can be used directly.
+pub trait AcceptorFactory<B: Body> {
+    type Acceptor: Acceptor<B>;
+
+    fn new_acceptor(&self, address: Address) -> Self::Acceptor;
+}
+
+impl<B: Body, A: Acceptor<B>, F: Fn(Address) -> A> AcceptorFactory<B> for F {
+    type Acceptor = A;
+
+    fn new_acceptor(&self, address: Address) -> A {
+        self(address)
+    }
+}
+
+/// Constructs a learner given its address and the addresses of all
+/// acceptors, see [`Builder::with_learners`].
+type LearnerFactory<B> = Box<dyn Fn(Address, Vec<Address>) -> Box<dyn Learner<B>>>;
+
+/// Builds a [`Simulator`] for any pair of proposer and acceptor
+/// implementations.
+///
+/// Proposers are named `p0`, `p1`, ... and acceptors `a0`, `a1`, ... . Each
+/// proposer is handed the addresses of all acceptors and its index as its
+/// unique [`Epoch`] identifier, as well as the addresses of all other
+/// proposers, see [`Proposer::set_peers`]. Proposers and the monitor share
+/// the same quorum system, see [`Builder::with_quorums`]. Learners, if any,
+/// are named `l0`, `l1`, ... and clients `c0`, `c1`, ... .
+pub struct Builder<B: Body, PF: ProposerFactory<B>, AF: AcceptorFactory<B>, Rng: rand::Rng> {
+    proposer_factory: PF,
+    acceptor_factory: AF,
+    proposers: u32,
+    acceptors: u32,
+    requests: Vec<Msg<B>>,
+    /// Indices passed to [`Builder::with_resubmission`] not referring to any
+    /// request, rejected by [`Builder::build`].
+    unknown_resubmissions: Vec<usize>,
+    msg_delay_rng: Option<Rng>,
+    network: Network,
+    /// Whether a network model or faults were set, both requiring
+    /// [`Builder::with_msg_delay_rng`].
+    network_needs_rng: bool,
+    crashes: Vec<Crash>,
+    monitor: bool,
+    quorums: Box<dyn QuorumSystem>,
+    learners: u32,
+    learner_factory: Option<LearnerFactory<B>>,
+    learning: Learning,
+    /// Timeout and maximum number of attempts of simulated clients, see
+    /// [`Builder::with_clients`].
+    clients: Option<(Instant, u32)>,
+    /// Timeouts of all proposers, see [`Builder::with_proposer_config`].
+    proposer_config: Option<ProposerConfig>,
+    body: PhantomData<B>,
+}
+
+impl<B: Body, PF: ProposerFactory<B>, AF: AcceptorFactory<B>, Rng: rand::Rng>
+    Builder<B, PF, AF, Rng>
+{
+    pub fn new(proposer_factory: PF, acceptor_factory: AF) -> Self {
+        Builder {
+            proposer_factory,
+            acceptor_factory,
+            proposers: 0,
+            acceptors: 0,
+            requests: vec![],
+            unknown_resubmissions: vec![],
+            msg_delay_rng: None,
+            network: Network::default(),
+            network_needs_rng: false,
+            crashes: vec![],
+            monitor: false,
+            quorums: Default::default(),
+            learners: 0,
+            learner_factory: None,
+            learning: Learning::Proposer,
+            clients: None,
+            proposer_config: None,
+            body: PhantomData,
+        }
+    }
+
+    pub fn with_proposers(mut self, size: u32) -> Self {
+        self.proposers = size;
+        self
+    }
+
+    pub fn with_acceptors(mut self, size: u32) -> Self {
+        self.acceptors = size;
+        self
+    }
+
+    /// Adds end-user requests, each given as the instant it is sent at and the
+    /// index of the proposer it is sent to. Each request carries a unique
+    /// value. All requests are sent by the first client, see
+    /// [`Builder::with_requests_from`].
+    pub fn with_requests(self, r: Vec<(u64, u32)>) -> Self {
+        self.with_requests_from(0, r)
+    }
+
+    /// Like [`Builder::with_requests`], sending the requests from the client
+    /// with the given index. Clients are distinct simulated endpoints, each
+    /// expecting a response to each of its own requests.
+    pub fn with_requests_from(mut self, client: u32, r: Vec<(u64, u32)>) -> Self {
+        for (instant, proposer) in r {
+            let request = new_request(self.requests.len(), client, proposer, Instant(instant));
+            self.requests.push(request);
+        }
+
+        self
+    }
+
+    /// Sends the request with the given index, see [`Builder::with_requests`],
+    /// once more at the given instant to the proposer with the given index,
+    /// e.g. as an end-user would after a timeout. Both carry the same
+    /// [`RequestId`], thus expecting a single answer.
+    pub fn with_resubmission(mut self, request: usize, instant: u64, proposer: u32) -> Self {
+        let mut resubmission = match self.requests.get(request) {
+            Some(r) => r.clone(),
+            None => {
+                self.unknown_resubmissions.push(request);
+                return self;
+            }
+        };
+        resubmission.header.to = proposer_address(proposer);
+        resubmission.header.at = Instant(instant);
+        self.requests.push(resubmission);
+        self
+    }
+
+    /// Enables emulated network delays. Without a random number generator
+    /// messages are delivered right away.
+    pub fn with_msg_delay_rng(mut self, rng: Rng) -> Self {
+        self.msg_delay_rng = Some(rng);
+        self
+    }
+
+    /// Sets the model used to delay messages, defaulting to
+    /// [`super::network::Exponential`]. Requires
+    /// [`Builder::with_msg_delay_rng`].
+    pub fn with_network_model<N: NetworkModel + 'static>(mut self, model: N) -> Self {
+        self.network.model = Box::new(model);
+        self.network_needs_rng = true;
+        self
+    }
+
+    /// Sets the faults injected into the emulated network. Like the network
+    /// model requires [`Builder::with_msg_delay_rng`].
+    pub fn with_faults(mut self, faults: Faults) -> Self {
+        self.network.faults = faults;
+        self.network_needs_rng = true;
+        self
+    }
+
+    /// Adds a partition to the schedule of network partitions. Partitions
+    /// apply regardless of [`Builder::with_msg_delay_rng`].
+    pub fn with_partition(mut self, partition: Partition) -> Self {
+        self.network.partitions.push(partition);
+        self
+    }
+
+    /// Adds a crash to the schedule of node crashes. The node needs to be part
+    /// of the cluster, and only acceptors can crash with amnesia, see
+    /// [`Crash::amnesia`].
+    pub fn with_crash(mut self, crash: Crash) -> Self {
+        self.crashes.push(crash);
+        self
+    }
+
+    /// Checks Paxos invariants after every tick, stopping the simulation on
+    /// the first violation, see [`super::Monitor`].
+    pub fn with_monitor(mut self) -> Self {
+        self.monitor = true;
+        self
+    }
+
+    /// Sets the quorum system of the proposers, defaulting to majorities,
+    /// e.g. [`crate::quorum::Quorums`] or [`crate::quorum::Grid`]. The
+    /// simulation refuses to run with quorums that do not intersect, see
+    /// [`crate::quorum::verify`].
+    pub fn with_quorums<Q: QuorumSystem + 'static>(mut self, quorums: Q) -> Self {
+        self.quorums = Box::new(quorums);
+        self
+    }
+
+    /// Adds the given number of learners, each constructed by the given
+    /// factory from its address and the addresses of all acceptors. Values
+    /// chosen reach the learners according to the given strategy.
+    pub fn with_learners<L, F>(mut self, size: u32, factory: F, learning: Learning) -> Self
+    where
+        L: Learner<B> + 'static,
+        F: Fn(Address, Vec<Address>) -> L + 'static,
+    {
+        self.learners = size;
+        self.learner_factory = Some(Box::new(move |address, acceptors| {
+            Box::new(factory(address, acceptors))
+        }));
+        self.learning = learning;
+        self
+    }
+
+    /// Has each client send its requests itself instead of injecting them
+    /// into the network, see [`super::Client`]. A request not responded to
+    /// within the given timeout is sent again to the next proposer, up to the
+    /// given number of attempts in total.
+    pub fn with_clients(mut self, timeout: Instant, attempts: u32) -> Self {
+        self.clients = Some((timeout, attempts));
+        self
+    }
+
+    /// Sets the timeouts of all proposers, overriding the ones the proposer
+    /// factory configured, see [`Proposer::set_config`].
+    pub fn with_proposer_config(mut self, config: ProposerConfig) -> Self {
+        self.proposer_config = Some(config);
+        self
+    }
+
+    /// Returns the simulator, or an error if the configuration can not be
+    /// simulated, e.g. simulated clients without any proposer to send their
+    /// requests to, a network model or faults without a random number
+    /// generator, a resubmission of an unknown request or a crash of an
+    /// unknown node.
+    pub fn build(self) -> Result<Simulator<AF::Acceptor, PF::Proposer, B, Rng>, String> {
+        if self.clients.is_some() && self.proposers == 0 {
+            return Err(
+                "expected at least one proposer for the clients to send requests to".into(),
+            );
+        }
+        if self.network_needs_rng && self.msg_delay_rng.is_none() {
+            return Err(
+                "expected a random number generator for the network model and faults".into(),
+            );
+        }
+        if let Some(i) = self.unknown_resubmissions.first() {
+            return Err(format!(
+                "expected request {} to resubmit, got {} requests",
+                i,
+                self.requests.len()
+            ));
+        }
+
+        let (mut proposers, mut acceptors) = build_nodes(
+            &self.proposer_factory,
+            &self.acceptor_factory,
+            self.proposers,
+            self.acceptors,
+            self.quorums.as_ref(),
+        );
+        if let Some(c) = self
+            .crashes
+            .iter()
+            .find(|c| c.amnesia && !acceptors.contains_key(&c.node))
+        {
+            return Err(format!(
+                "expected {:?} to be an acceptor, amnesia is only supported for acceptors",
+                c.node
+            ));
+        }
+        if let Some(config) = self.proposer_config {
+            for p in proposers.values_mut() {
+                p.set_config(config);
+            }
+        }
+
+        let mut learners: BTreeMap<Address, Box<dyn Learner<B>>> = BTreeMap::new();
+        if let Some(factory) = self.learner_factory.as_ref() {
+            let a_addresses: Vec<Address> = acceptors.keys().cloned().collect();
+            let l_addresses: Vec<Address> = (0..self.learners)
+                .map(|i| Address::new(&format!("l{}", i)))
+                .collect();
+
+            for address in l_addresses.iter() {
+                let mut learner = factory(address.clone(), a_addresses.clone());
+                learner.set_quorums(self.quorums.clone());
+                learner.set_learners(l_addresses.clone(), self.learning);
+                learners.insert(address.clone(), learner);
+            }
+            for p in proposers.values_mut() {
+                p.set_learners(l_addresses.clone(), self.learning);
+            }
+            for a in acceptors.values_mut() {
+                a.set_learners(l_addresses.clone(), self.learning);
+            }
+        }
+
+        // Only simulated clients can crash, requests are injected otherwise.
+        let clients: BTreeSet<&Address> = match self.clients {
+            Some(_) => self.requests.iter().map(|r| &r.header.from).collect(),
+            None => BTreeSet::new(),
+        };
+        if let Some(c) = self.crashes.iter().find(|c| {
+            !proposers.contains_key(&c.node)
+                && !acceptors.contains_key(&c.node)
+                && !learners.contains_key(&c.node)
+                && !clients.contains(&c.node)
+        }) {
+            return Err(format!("expected {:?} to be a node to crash", c.node));
+        }
+
+        let mut simulator = Simulator::new(
+            proposers,
+            acceptors,
+            self.requests,
+            self.msg_delay_rng,
+            self.network,
+            self.crashes,
+            if self.monitor {
+                Some(Monitor::with_quorums(self.quorums.clone()))
+            } else {
+                None
+            },
+        );
+        simulator.quorums = self.quorums;
+        simulator.learners = learners;
+
+        if let Some((timeout, attempts)) = self.clients {
+            let p_addresses: Vec<Address> = (0..self.proposers).map(proposer_address).collect();
+            let mut workloads: BTreeMap<Address, Vec<Msg<B>>> = BTreeMap::new();
+            for r in simulator.requests() {
+                workloads
+                    .entry(r.header.from.clone())
+                    .or_default()
+                    .push(r.clone());
+            }
+            let clients = workloads
+                .into_iter()
+                .map(|(address, workload)| {
+                    let client = Client::new(
+                        address.clone(),
+                        p_addresses.clone(),
+                        workload,
+                        timeout,
+                        attempts,
+                    );
+                    (address, client)
+                })
+                .collect();
+            simulator.simulate_clients(clients);
+        }
+
+        Ok(simulator)
+    }
+}
+
+/// Returns the given number of proposers and acceptors, named and
+/// configured as described in [`Builder`].
+pub(super) fn build_nodes<B: Body, PF: ProposerFactory<B>, AF: AcceptorFactory<B>>(
+    proposer_factory: &PF,
+    acceptor_factory: &AF,
+    proposers: u32,
+    acceptors: u32,
+    quorums: &dyn QuorumSystem,
+) -> (
+    BTreeMap<Address, PF::Proposer>,
+    BTreeMap<Address, AF::Acceptor>,
+) {
+    let acceptors: BTreeMap<Address, AF::Acceptor> = (0..acceptors)
+        .map(|i| {
+            let address = Address::new(&format!("a{}", i));
+            (address.clone(), acceptor_factory.new_acceptor(address))
+        })
+        .collect();
+    let a_addresses: Vec<Address> = acceptors.keys().cloned().collect();
+
+    let p_addresses: Vec<Address> = (0..proposers).map(proposer_address).collect();
+
+    let proposers = p_addresses
+        .iter()
+        .enumerate()
+        .map(|(i, address)| {
+            let mut proposer = proposer_factory.new_proposer(
+                address.clone(),
+                Epoch::new(0, i as u32),
+                a_addresses.clone(),
+            );
+            proposer.set_peers(
+                p_addresses
+                    .iter()
+                    .filter(|p| *p != address)
+                    .cloned()
+                    .collect(),
+            );
+            proposer.set_quorums(quorums.box_clone());
+            (address.clone(), proposer)
+        })
+        .collect();
+
+    (proposers, acceptors)
+}
+
+/// Returns the `i`th end-user request, carrying the identifier `i` and the
+/// unique value `v{i}`.
+pub(super) fn new_request<B: Body>(i: usize, client: u32, proposer: u32, at: Instant) -> Msg<B> {
+    Msg {
+        header: Header {
+            from: client_address(client),
+            to: proposer_address(proposer),
+            at,
+        },
+        body: B::new_request(RequestId(i as u64), Value::new(&format!("v{}", i))),
+    }
+}
+
+fn proposer_address(i: u32) -> Address {
+    Address::new(&format!("p{}", i))
+}
+
+fn client_address(i: u32) -> Address {
+    Address::new(&format!("c{}", i))
+}
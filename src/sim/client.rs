@@ -0,0 +1,207 @@
+use crate::{Address, Body, Header, Instant, Msg, Node, RequestId, Value};
+use std::collections::VecDeque;
+
+/// What happened to a single request of a [`Client`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestHistory {
+    pub id: RequestId,
+    pub value: Value,
+    /// Instant the request was first sent at.
+    pub sent_at: Instant,
+    /// Instant and proposer of each attempt, the first one included.
+    pub attempts: Vec<(Instant, Address)>,
+    /// Number of attempts a busy proposer rejected, see
+    /// [`Body::is_rejection`].
+    pub rejections: u32,
+    /// Instant the first response arrived at, if any.
+    pub answered_at: Option<Instant>,
+    /// Whether the client stopped waiting for a response, either running out
+    /// of attempts or crashing.
+    pub abandoned: bool,
+}
+
+impl RequestHistory {
+    /// Returns the time from sending the request until the first response,
+    /// in simulated time.
+    pub fn latency(&self) -> Option<Instant> {
+        self.answered_at.map(|a| a - self.sent_at)
+    }
+}
+
+/// An end-user issuing requests according to a workload.
+///
+/// Each request is sent at its instant to its proposer. Without a response
+/// within the timeout the request is sent again to the next proposer, up to
+/// the given number of attempts in total. A rejected attempt is retried right
+/// away. Responses are matched to requests by their [`RequestId`].
+#[derive(Clone, Debug)]
+pub struct Client<B: Body> {
+    address: Address,
+    proposers: Vec<Address>,
+    timeout: Instant,
+    attempts: u32,
+    /// Requests not yet sent, ordered by the instant they are due at.
+    workload: VecDeque<Msg<B>>,
+    inbox: VecDeque<Msg<B>>,
+    /// Index into `history` of each request still waiting for a response.
+    outstanding: Vec<usize>,
+    history: Vec<RequestHistory>,
+}
+
+impl<B: Body> Node<B> for Client<B> {
+    fn receive(&mut self, m: Msg<B>) {
+        self.inbox.push_back(m);
+    }
+
+    fn process(&mut self, now: Instant) -> Vec<Msg<B>> {
+        self.process(now)
+    }
+
+    fn crash(&mut self, _now: Instant) {
+        // Requests in flight are given up on, the remaining workload is sent
+        // once recovered.
+        self.inbox.clear();
+        for i in self.outstanding.drain(0..) {
+            self.history[i].abandoned = true;
+        }
+    }
+}
+
+impl<B: Body> Client<B> {
+    /// Returns a client sending the given requests, each at the instant and
+    /// to the proposer given by its header. Retries go to the other given
+    /// proposers in turn.
+    pub fn new(
+        address: Address,
+        proposers: Vec<Address>,
+        mut workload: Vec<Msg<B>>,
+        timeout: Instant,
+        attempts: u32,
+    ) -> Self {
+        workload.sort_by_key(|m| m.header.at);
+        Client {
+            address,
+            proposers,
+            timeout,
+            attempts,
+            workload: workload.into(),
+            inbox: VecDeque::new(),
+            outstanding: vec![],
+            history: vec![],
+        }
+    }
+
+    pub fn address(&self) -> Address {
+        self.address.clone()
+    }
+
+    /// Requests sent so far, in the order they were sent in.
+    pub fn history(&self) -> &[RequestHistory] {
+        &self.history
+    }
+
+    /// Whether the client neither has requests left to send nor waits for
+    /// any response.
+    pub fn is_idle(&self) -> bool {
+        self.workload.is_empty() && self.outstanding.is_empty()
+    }
+
+    fn process(&mut self, now: Instant) -> Vec<Msg<B>> {
+        // Requests whose last attempt was rejected.
+        let mut rejected = vec![];
+        for m in self.inbox.drain(0..).collect::<Vec<_>>() {
+            if let Some(id) = m.body.is_rejection() {
+                if let Some(i) = self.outstanding.iter().find(|i| self.history[**i].id == id) {
+                    let h = &mut self.history[*i];
+                    // Ignore rejections of earlier attempts.
+                    if h.attempts.last().map(|(_, p)| *p == m.header.from) == Some(true) {
+                        h.rejections += 1;
+                        rejected.push(*i);
+                    }
+                }
+                continue;
+            }
+
+            let id = match m.body.is_response() {
+                Some((id, _)) => id,
+                None => unimplemented!(),
+            };
+            // Duplicate responses, e.g. to a retried request, are ignored.
+            if let Some(i) = self
+                .outstanding
+                .iter()
+                .position(|i| self.history[*i].id == id)
+            {
+                let h = self.outstanding.remove(i);
+                self.history[h].answered_at = Some(now);
+            }
+        }
+
+        let mut sent = vec![];
+
+        while self
+            .workload
+            .front()
+            .map(|m| m.header.at <= now)
+            .unwrap_or(false)
+        {
+            let request = self.workload.pop_front().unwrap();
+            let (id, value) = request.body.is_request().expect("workload to be requests");
+            self.outstanding.push(self.history.len());
+            self.history.push(RequestHistory {
+                id,
+                value,
+                sent_at: now,
+                attempts: vec![(now, request.header.to.clone())],
+                rejections: 0,
+                answered_at: None,
+                abandoned: false,
+            });
+            sent.push(self.send(request.header.to, request.body, now));
+        }
+
+        let mut abandoned = vec![];
+        let mut retries = vec![];
+        let proposers = &self.proposers;
+        for i in self.outstanding.iter() {
+            let h = &mut self.history[*i];
+            let (last_sent_at, last_proposer) = h.attempts.last().cloned().unwrap();
+            if now < last_sent_at + self.timeout && !rejected.contains(i) {
+                continue;
+            }
+
+            if h.attempts.len() as u32 >= self.attempts {
+                h.abandoned = true;
+                abandoned.push(*i);
+                continue;
+            }
+
+            let next = proposers
+                .iter()
+                .position(|p| *p == last_proposer)
+                .map(|p| (p + 1) % proposers.len())
+                .unwrap_or(0);
+            let proposer = proposers[next].clone();
+            h.attempts.push((now, proposer.clone()));
+            retries.push((proposer, B::new_request(h.id, h.value.clone())));
+        }
+        self.outstanding.retain(|i| !abandoned.contains(i));
+
+        for (proposer, body) in retries {
+            sent.push(self.send(proposer, body, now));
+        }
+
+        sent
+    }
+
+    fn send(&self, to: Address, body: B, now: Instant) -> Msg<B> {
+        Msg {
+            header: Header {
+                from: self.address.clone(),
+                to,
+                at: now + 1,
+            },
+            body,
+        }
+    }
+}
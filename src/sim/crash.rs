@@ -0,0 +1,58 @@
+use crate::{Address, Instant};
+
+/// A crash of a single node at `at`, optionally followed by a recovery at
+/// `recover_at`.
+///
+/// On crash the node loses its volatile state, see [`crate::Node::crash`].
+/// While crashed it neither processes nor receives any messages.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Crash {
+    pub node: Address,
+    pub at: Instant,
+    pub recover_at: Option<Instant>,
+    /// Whether the node loses its persisted state as well, see
+    /// [`crate::Acceptor::amnesia`]. Only supported for acceptors.
+    pub amnesia: bool,
+}
+
+impl Crash {
+    /// Returns a crash the node never recovers from.
+    pub fn stop(node: Address, at: Instant) -> Self {
+        Crash {
+            node,
+            at,
+            recover_at: None,
+            amnesia: false,
+        }
+    }
+
+    /// Returns a crash the node recovers from at `recover_at`.
+    pub fn recover(node: Address, at: Instant, recover_at: Instant) -> Self {
+        Crash {
+            node,
+            at,
+            recover_at: Some(recover_at),
+            amnesia: false,
+        }
+    }
+
+    /// Have the node lose its persisted state on crash, thus restarting
+    /// blank. Only supported for acceptors, see [`super::Builder::build`].
+    pub fn amnesia(mut self) -> Self {
+        self.amnesia = true;
+        self
+    }
+}
+
+impl std::fmt::Display for Crash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.recover_at {
+            Some(r) => write!(f, "{:?} at {:?} until {:?}", self.node, self.at, r)?,
+            None => write!(f, "{:?} at {:?}", self.node, self.at)?,
+        }
+        if self.amnesia {
+            write!(f, " with amnesia")?;
+        }
+        Ok(())
+    }
+}
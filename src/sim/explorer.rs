@@ -0,0 +1,407 @@
+use super::builder::{build_nodes, new_request};
+use super::{AcceptorFactory, Monitor, ProposerFactory, Violation};
+use crate::quorum::QuorumSystem;
+use crate::{Acceptor, Address, Body, Instant, Msg, Proposer};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+
+/// Clock advance of a timeout at least, beyond the proposer timeouts also
+/// covering e.g. leader election.
+const MIN_TIMEOUT_STEP: u64 = 100;
+
+/// Explores all interleavings of message deliveries of a small cluster,
+/// checking the invariants of [`super::Monitor`] in every reachable state.
+///
+/// Instead of sampling schedules via random message delays like
+/// [`super::Simulator`], the explorer does a breadth-first search over all
+/// choices of which in-flight message to deliver or drop next, thus finding
+/// a shortest counterexample if there is any. States already visited are
+/// recognized by the debug representation of all node states and in-flight
+/// messages. Nodes need to be cloneable to branch off into each choice.
+///
+/// Time only advances through explicit timeouts, see
+/// [`Explorer::with_timeouts`].
+pub struct Explorer<B: Body, PF: ProposerFactory<B>, AF: AcceptorFactory<B>> {
+    proposer_factory: PF,
+    acceptor_factory: AF,
+    proposers: u32,
+    acceptors: u32,
+    requests: Vec<Msg<B>>,
+    drops: bool,
+    max_timeouts: usize,
+    max_amnesia: usize,
+    max_depth: usize,
+    quorums: Box<dyn QuorumSystem>,
+}
+
+/// Summary of an exploration that did not find any invariant violation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Exploration {
+    /// Number of distinct states visited.
+    pub states: usize,
+    /// Whether some paths were cut short by the maximum depth, thus not all
+    /// reachable states were visited.
+    pub truncated: bool,
+}
+
+/// A shortest sequence of steps leading to an invariant violation.
+#[derive(Clone, Debug)]
+pub struct Counterexample {
+    pub violation: Violation,
+    pub trace: Vec<String>,
+}
+
+impl std::fmt::Display for Counterexample {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.violation)?;
+        for (i, step) in self.trace.iter().enumerate() {
+            writeln!(f, "{:>3}: {}", i + 1, step)?;
+        }
+        Ok(())
+    }
+}
+
+/// A single nondeterministic choice taken while exploring.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Choice {
+    /// Deliver the in-flight message at the given index.
+    Deliver(usize),
+    /// Drop the in-flight message at the given index.
+    Drop(usize),
+    /// Advance the clock past the proposer timeouts.
+    Timeout,
+    /// Have the acceptor at the given index lose all its state.
+    Amnesia(usize),
+}
+
+impl<B, PF, AF> Explorer<B, PF, AF>
+where
+    B: Body,
+    PF: ProposerFactory<B>,
+    PF::Proposer: Clone + std::fmt::Debug,
+    AF: AcceptorFactory<B>,
+    AF::Acceptor: Clone + std::fmt::Debug,
+{
+    pub fn new(proposer_factory: PF, acceptor_factory: AF) -> Self {
+        Explorer {
+            proposer_factory,
+            acceptor_factory,
+            proposers: 0,
+            acceptors: 0,
+            requests: vec![],
+            drops: false,
+            max_timeouts: 0,
+            max_amnesia: 0,
+            max_depth: 100,
+            quorums: Default::default(),
+        }
+    }
+
+    pub fn with_proposers(mut self, size: u32) -> Self {
+        self.proposers = size;
+        self
+    }
+
+    pub fn with_acceptors(mut self, size: u32) -> Self {
+        self.acceptors = size;
+        self
+    }
+
+    /// Adds end-user requests, each given as the index of the proposer it is
+    /// sent to. Like with [`super::Builder::with_requests`] each request
+    /// carries a unique value.
+    pub fn with_requests(mut self, r: Vec<u32>) -> Self {
+        for proposer in r {
+            let request = new_request(self.requests.len(), 0, proposer, Instant(0));
+            self.requests.push(request);
+        }
+
+        self
+    }
+
+    /// Explores dropping each in-flight message besides delivering it.
+    ///
+    /// As far as safety is concerned a dropped message is no different from
+    /// one that is never delivered, which is explored regardless. Drops thus
+    /// mostly widen the set of visited states.
+    pub fn with_drops(mut self) -> Self {
+        self.drops = true;
+        self
+    }
+
+    /// Explores up to `max` timeouts, each advancing the clock far enough for
+    /// all pending proposer timeouts to fire.
+    pub fn with_timeouts(mut self, max: usize) -> Self {
+        self.max_timeouts = max;
+        self
+    }
+
+    /// Explores up to `max` acceptors restarting blank, see
+    /// [`crate::Acceptor::amnesia`].
+    pub fn with_amnesia(mut self, max: usize) -> Self {
+        self.max_amnesia = max;
+        self
+    }
+
+    /// Bounds the number of steps along each path, defaulting to 100.
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Sets the quorum system of the proposers, defaulting to majorities.
+    ///
+    /// Unlike [`super::Simulator::run`] the explorer accepts quorums that do
+    /// not intersect, e.g. to find out how they break safety.
+    pub fn with_quorums<Q: QuorumSystem + 'static>(mut self, quorums: Q) -> Self {
+        self.quorums = Box::new(quorums);
+        self
+    }
+
+    /// Explores all reachable states, returning a shortest counterexample on
+    /// the first invariant violation found.
+    pub fn run(&self) -> Result<Exploration, Counterexample> {
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        let mut truncated = false;
+
+        let initial = self.initial();
+        visited.insert(initial.key());
+        queue.push_back((initial, vec![]));
+
+        while let Some((world, path)) = queue.pop_front() {
+            let choices = world.choices(self);
+            if choices.is_empty() {
+                continue;
+            }
+            if path.len() >= self.max_depth {
+                truncated = true;
+                continue;
+            }
+
+            for c in choices {
+                let mut next = world.clone();
+                let mut next_path = path.clone();
+                next_path.push(c);
+
+                if let (_, Some(_)) = next.apply(c) {
+                    return Err(self.counterexample(&next_path));
+                }
+                if visited.insert(next.key()) {
+                    queue.push_back((next, next_path));
+                }
+            }
+        }
+
+        Ok(Exploration {
+            states: visited.len(),
+            truncated,
+        })
+    }
+
+    fn initial(&self) -> World<PF::Proposer, AF::Acceptor, B> {
+        let (proposers, acceptors) = build_nodes(
+            &self.proposer_factory,
+            &self.acceptor_factory,
+            self.proposers,
+            self.acceptors,
+            self.quorums.as_ref(),
+        );
+        let mut world = World {
+            now: Instant(0),
+            proposers,
+            acceptors,
+            in_flight: vec![],
+            monitor: Monitor::with_quorums(self.quorums.clone()),
+            timeouts: 0,
+            amnesia: 0,
+        };
+        let acceptors: Vec<Address> = world.acceptors.keys().cloned().collect();
+        world.enqueue(
+            self.requests
+                .iter()
+                .flat_map(|r| B::submit(r.clone(), &acceptors))
+                .collect(),
+        );
+        world
+    }
+
+    /// Replays the given choices, describing each step along the way, up to
+    /// the violation they lead to.
+    fn counterexample(&self, path: &[Choice]) -> Counterexample {
+        let mut world = self.initial();
+        let mut trace = vec![];
+
+        for c in path {
+            let mut step = world.describe(*c);
+            let (responses, violation) = world.apply(*c);
+            for r in responses {
+                step.push_str(&format!(", responding {:?}", r.body));
+            }
+            trace.push(step);
+
+            if let Some(violation) = violation {
+                return Counterexample { violation, trace };
+            }
+        }
+
+        unreachable!("path to not lead to a violation")
+    }
+}
+
+/// State of the cluster reached by a sequence of choices.
+#[derive(Clone)]
+struct World<P, A, B: Body> {
+    now: Instant,
+    proposers: BTreeMap<Address, P>,
+    acceptors: BTreeMap<Address, A>,
+    /// Messages sent but neither delivered nor dropped yet, kept sorted to
+    /// make the state independent of the order they were sent in.
+    in_flight: Vec<Msg<B>>,
+    monitor: Monitor,
+    timeouts: usize,
+    amnesia: usize,
+}
+
+impl<P, A, B> World<P, A, B>
+where
+    P: Proposer<B> + Clone + std::fmt::Debug,
+    A: Acceptor<B> + Clone + std::fmt::Debug,
+    B: Body,
+{
+    fn choices<PF: ProposerFactory<B>, AF: AcceptorFactory<B>>(
+        &self,
+        explorer: &Explorer<B, PF, AF>,
+    ) -> Vec<Choice> {
+        let mut choices = vec![];
+
+        for (i, m) in self.in_flight.iter().enumerate() {
+            // Delivering or dropping either of two equal messages leads to the
+            // same state.
+            if i > 0 && self.in_flight[i - 1] == *m {
+                continue;
+            }
+
+            choices.push(Choice::Deliver(i));
+            if explorer.drops {
+                choices.push(Choice::Drop(i));
+            }
+        }
+
+        if self.timeouts < explorer.max_timeouts {
+            choices.push(Choice::Timeout);
+        }
+
+        if self.amnesia < explorer.max_amnesia {
+            choices.extend((0..self.acceptors.len()).map(Choice::Amnesia));
+        }
+
+        choices
+    }
+
+    /// Returns how far to advance the clock for all pending proposer
+    /// timeouts to fire, see [`crate::timeout::ProposerConfig::max_timeout`].
+    fn timeout_step(&self) -> u64 {
+        self.proposers
+            .values()
+            .map(|p| p.config().max_timeout().0)
+            .fold(MIN_TIMEOUT_STEP, u64::max)
+    }
+
+    fn describe(&self, c: Choice) -> String {
+        match c {
+            Choice::Deliver(i) => format!("deliver {:?}", self.in_flight[i]),
+            Choice::Drop(i) => format!("drop {:?}", self.in_flight[i]),
+            Choice::Timeout => format!("timeout, now {:?}", self.now + self.timeout_step()),
+            Choice::Amnesia(i) => {
+                format!("amnesia of {:?}", self.acceptors.keys().nth(i).unwrap())
+            }
+        }
+    }
+
+    /// Applies the given choice, returning the responses sent to end-users
+    /// and the invariant violation it caused, if any.
+    fn apply(&mut self, c: Choice) -> (Vec<Msg<B>>, Option<Violation>) {
+        let sent = match c {
+            Choice::Deliver(i) => {
+                let m = self.in_flight.remove(i);
+                let to = m.header.to.clone();
+                match self.proposers.get_mut(&to) {
+                    Some(p) => {
+                        p.receive(m);
+                        p.process(self.now)
+                    }
+                    None => match self.acceptors.get_mut(&to) {
+                        Some(a) => {
+                            a.receive(m);
+                            a.process(self.now)
+                        }
+                        None => panic!("{:?} is not a known acceptor nor proposer", to),
+                    },
+                }
+            }
+            Choice::Drop(i) => {
+                self.in_flight.remove(i);
+                vec![]
+            }
+            Choice::Timeout => {
+                self.timeouts += 1;
+                self.now = self.now + self.timeout_step();
+                let mut sent = vec![];
+                for p in self.proposers.values_mut() {
+                    sent.append(&mut p.process(self.now));
+                }
+                for a in self.acceptors.values_mut() {
+                    sent.append(&mut a.process(self.now));
+                }
+                sent
+            }
+            Choice::Amnesia(i) => {
+                self.amnesia += 1;
+                let a = self.acceptors.values_mut().nth(i).unwrap();
+                a.amnesia(self.now);
+                vec![]
+            }
+        };
+
+        let states = self
+            .acceptors
+            .iter()
+            .map(|(address, a)| (address.clone(), a.states()))
+            .collect();
+        let violation = self.monitor.check(self.now, states, &sent).err();
+
+        // Responses to end-users leave the system right away.
+        let (responses, sent): (Vec<_>, Vec<_>) = sent.into_iter().partition(|m| {
+            !self.proposers.contains_key(&m.header.to) && !self.acceptors.contains_key(&m.header.to)
+        });
+        self.enqueue(sent);
+
+        (responses, violation)
+    }
+
+    fn enqueue(&mut self, msgs: Vec<Msg<B>>) {
+        self.in_flight.extend(msgs.into_iter().map(|mut m| {
+            // The instant a message was sent at is irrelevant to its delivery
+            // and would only make otherwise equal states differ.
+            m.header.at = Instant(0);
+            m
+        }));
+        self.in_flight.sort_by_cached_key(|m| format!("{:?}", m));
+    }
+
+    /// Returns the state of all nodes and in-flight messages, kept in full
+    /// rather than hashed, thus distinct states never being mistaken for one
+    /// another.
+    fn key(&self) -> String {
+        format!(
+            "{:?} {:?} {:?} {:?} {:?} {} {}",
+            self.now,
+            self.proposers,
+            self.acceptors,
+            self.in_flight,
+            self.monitor,
+            self.timeouts,
+            self.amnesia
+        )
+    }
+}
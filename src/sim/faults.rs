@@ -0,0 +1,47 @@
+use crate::Address;
+use std::collections::HashMap;
+
+/// Probabilities of faults on a single directed link between two nodes.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LinkFaults {
+    /// Probability of a message being lost.
+    pub drop: f64,
+    /// Probability of a message being delivered twice.
+    pub duplicate: f64,
+    /// Probability of a message being held back, thus likely being overtaken
+    /// by messages sent after it.
+    pub reorder: f64,
+}
+
+/// Fault injection configuration of the emulated network.
+///
+/// Faults are only injected on links between proposers and acceptors, never
+/// on links to or from end-users.
+#[derive(Clone, Debug, Default)]
+pub struct Faults {
+    default: LinkFaults,
+    links: HashMap<(Address, Address), LinkFaults>,
+}
+
+impl Faults {
+    /// Returns a configuration applying the given faults to every link.
+    pub fn new(default: LinkFaults) -> Self {
+        Faults {
+            default,
+            links: HashMap::new(),
+        }
+    }
+
+    /// Overrides the faults of the directed link from `from` to `to`.
+    pub fn with_link(mut self, from: Address, to: Address, faults: LinkFaults) -> Self {
+        self.links.insert((from, to), faults);
+        self
+    }
+
+    pub fn link(&self, from: &Address, to: &Address) -> LinkFaults {
+        self.links
+            .get(&(from.clone(), to.clone()))
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
@@ -0,0 +1,26 @@
+//! Discrete event simulation of a set of proposers and acceptors exchanging
+//! messages over an emulated network.
+//!
+//! Only available with the `sim` cargo feature enabled.
+
+pub use builder::{AcceptorFactory, Builder, ProposerFactory};
+pub use client::{Client, RequestHistory};
+pub use crash::Crash;
+pub use explorer::{Counterexample, Exploration, Explorer};
+pub use faults::{Faults, LinkFaults};
+pub use monitor::{Invariant, Monitor, Violation};
+pub use network::{Network, NetworkModel};
+pub use partition::{Cut, Partition};
+pub use simulator::{Delivery, Simulator, Stats};
+pub use storage::SimulatedStorage;
+
+mod builder;
+mod client;
+mod crash;
+mod explorer;
+mod faults;
+mod monitor;
+pub mod network;
+mod partition;
+mod simulator;
+mod storage;
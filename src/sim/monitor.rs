@@ -0,0 +1,190 @@
+use crate::quorum::QuorumSystem;
+use crate::storage::AcceptorState;
+use crate::{Address, Body, Epoch, Instant, Msg, Slot, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Paxos safety invariants checked by the [`Monitor`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Invariant {
+    /// Once a value is chosen in a slot, i.e. accepted by a quorum of
+    /// acceptors in the same epoch, no other value is ever chosen in that
+    /// slot. See [`Body::is_chosen`] for what makes a quorum given the
+    /// [`QuorumSystem`] of the monitor.
+    SingleChosenValue,
+    /// An acceptor never accepts a proposal with an epoch lower than the one
+    /// it promised.
+    AcceptAtOrAbovePromise,
+    /// Once a value is chosen in a slot, every proposal for that slot with a
+    /// higher epoch carries that value.
+    ProposeChosenValue,
+}
+
+/// First violation of an [`Invariant`] found by the [`Monitor`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+    pub at: Instant,
+    pub invariant: Invariant,
+    pub nodes: Vec<Address>,
+    pub details: String,
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "violated {:?} at {:?} by {:?}: {}",
+            self.invariant, self.at, self.nodes, self.details
+        )
+    }
+}
+
+/// Omniscient observer checking Paxos invariants against the state of all
+/// acceptors and the proposals sent. Each log slot is checked on its own.
+///
+/// A value counts as chosen once a quorum accepted it at some point, even if
+/// some of the acceptors accepted another value since. Recording the state
+/// of an acceptor after each message delivered to it, see
+/// [`Monitor::record`], thus catches a value chosen and overwritten within
+/// the same tick.
+#[derive(Clone, Debug, Default)]
+pub struct Monitor {
+    /// Latest recorded state of each acceptor.
+    states: BTreeMap<Address, BTreeMap<Slot, AcceptorState>>,
+    /// Acceptors that accepted each value in each slot and epoch so far.
+    votes: BTreeMap<(Slot, Epoch, Value), BTreeSet<Address>>,
+    /// First chosen value of each slot along with the epoch it was chosen in.
+    chosen: BTreeMap<Slot, (Epoch, Value)>,
+    quorums: Box<dyn QuorumSystem>,
+}
+
+impl Monitor {
+    /// Returns a monitor considering a value chosen once accepted by a
+    /// phase-2 quorum of the given quorum system, thus matching the
+    /// proposers.
+    pub fn with_quorums(quorums: Box<dyn QuorumSystem>) -> Self {
+        Monitor {
+            quorums,
+            ..Default::default()
+        }
+    }
+
+    /// Records the states of all acceptors, see [`Monitor::record`], and
+    /// checks the given proposals sent against the values chosen so far.
+    pub fn check<B: Body>(
+        &mut self,
+        now: Instant,
+        states: BTreeMap<Address, BTreeMap<Slot, AcceptorState>>,
+        sent: &[Msg<B>],
+    ) -> Result<(), Violation> {
+        // Knowing all acceptors before counting any votes.
+        for address in states.keys() {
+            self.states.entry(address.clone()).or_default();
+        }
+        for (address, slots) in states {
+            self.record::<B>(now, address, slots)?;
+        }
+
+        for m in sent {
+            let (e, v) = match m.body.is_propose() {
+                Some(p) => p,
+                None => continue,
+            };
+            let slot = m.body.slot();
+            match self.chosen.get(&slot) {
+                Some((chosen_epoch, chosen)) if e > *chosen_epoch && v != *chosen => {
+                    return Err(Violation {
+                        at: now,
+                        invariant: Invariant::ProposeChosenValue,
+                        nodes: vec![m.header.from.clone(), m.header.to.clone()],
+                        details: format!(
+                            "proposed {:?} at {:?} in {:?} after {:?} was chosen at {:?}",
+                            v, e, slot, chosen, chosen_epoch
+                        ),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Records the current state of the given acceptor, counting the values
+    /// it accepted since it was last recorded. Values are chosen out of the
+    /// acceptors passed to [`Monitor::check`] before.
+    pub fn record<B: Body>(
+        &mut self,
+        now: Instant,
+        address: Address,
+        slots: BTreeMap<Slot, AcceptorState>,
+    ) -> Result<(), Violation> {
+        let previous = self
+            .states
+            .insert(address.clone(), slots.clone())
+            .unwrap_or_default();
+
+        // The promise is not necessarily tracked per slot, thus taking the
+        // highest of any slot.
+        if let Some(promised) = previous.values().filter_map(|s| s.promised_epoch).max() {
+            for (slot, state) in slots.iter() {
+                let previously_accepted = previous.get(slot).and_then(|s| s.accepted.as_ref());
+                match &state.accepted {
+                    Some((e, v))
+                        if *e < promised && previously_accepted != state.accepted.as_ref() =>
+                    {
+                        return Err(Violation {
+                            at: now,
+                            invariant: Invariant::AcceptAtOrAbovePromise,
+                            nodes: vec![address],
+                            details: format!(
+                                "accepted {:?} at {:?} in {:?} after promising {:?}",
+                                v, e, slot, promised
+                            ),
+                        });
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let all: Vec<Address> = self.states.keys().cloned().collect();
+        for (slot, state) in slots {
+            let (epoch, v) = match state.accepted {
+                Some(accepted) => accepted,
+                None => continue,
+            };
+            let votes = self.votes.entry((slot, epoch, v.clone())).or_default();
+            if !votes.insert(address.clone())
+                || !B::is_chosen(epoch, &all, votes, self.quorums.as_ref())
+            {
+                continue;
+            }
+
+            match self.chosen.get(&slot) {
+                None => {
+                    self.chosen.insert(slot, (epoch, v));
+                }
+                Some((chosen_epoch, chosen)) if *chosen != v => {
+                    return Err(Violation {
+                        at: now,
+                        invariant: Invariant::SingleChosenValue,
+                        nodes: votes.iter().cloned().collect(),
+                        details: format!(
+                            "chose {:?} at {:?} in {:?} after {:?} was chosen at {:?}",
+                            v, epoch, slot, chosen, chosen_epoch
+                        ),
+                    });
+                }
+                Some(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the first value chosen in each slot so far along with its
+    /// epoch.
+    pub fn chosen(&self) -> &BTreeMap<Slot, (Epoch, Value)> {
+        &self.chosen
+    }
+}
@@ -0,0 +1,170 @@
+use super::{Faults, Partition};
+use crate::{Header, Instant};
+use rand::distributions::Distribution;
+use rand::{Rng, RngCore};
+
+/// The emulated network connecting all nodes of a simulation.
+#[derive(Debug)]
+pub struct Network {
+    pub model: Box<dyn NetworkModel>,
+    pub faults: Faults,
+    /// Schedule of network partitions.
+    pub partitions: Vec<Partition>,
+}
+
+impl Default for Network {
+    fn default() -> Self {
+        Network {
+            model: Box::new(Exponential::default()),
+            faults: Faults::default(),
+            partitions: vec![],
+        }
+    }
+}
+
+/// Decides when and whether a message sent over the emulated network is
+/// delivered.
+pub trait NetworkModel: std::fmt::Debug {
+    /// Returns the delay to add on top of `header.at` for the message sent
+    /// from `header.from` to `header.to` at `now`, or `None` if the message is
+    /// lost.
+    fn delay(&mut self, rng: &mut dyn RngCore, header: &Header, now: Instant) -> Option<Instant>;
+}
+
+impl<N: NetworkModel + ?Sized> NetworkModel for Box<N> {
+    fn delay(&mut self, rng: &mut dyn RngCore, header: &Header, now: Instant) -> Option<Instant> {
+        (**self).delay(rng, header, now)
+    }
+}
+
+/// Delays every message by the same amount.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Constant(pub Instant);
+
+impl NetworkModel for Constant {
+    fn delay(&mut self, _: &mut dyn RngCore, _: &Header, _: Instant) -> Option<Instant> {
+        Some(self.0)
+    }
+}
+
+/// Delays messages uniformly within `min..=max`.
+#[derive(Clone, Copy, Debug)]
+pub struct Uniform {
+    min: Instant,
+    max: Instant,
+}
+
+impl Uniform {
+    pub fn new(min: Instant, max: Instant) -> Self {
+        assert!(
+            min <= max,
+            "expected min {:?} to not exceed max {:?}",
+            min,
+            max
+        );
+        Uniform { min, max }
+    }
+}
+
+impl NetworkModel for Uniform {
+    fn delay(&mut self, rng: &mut dyn RngCore, _: &Header, _: Instant) -> Option<Instant> {
+        Some(Instant(rng.gen_range(self.min.0, self.max.0 + 1)))
+    }
+}
+
+/// Delays messages based on an exponential distribution, capped at `max`.
+#[derive(Clone, Copy, Debug)]
+pub struct Exponential {
+    distr: rand_distr::Exp<f64>,
+    max: Instant,
+}
+
+impl Exponential {
+    pub fn new(rate: f64, max: Instant) -> Self {
+        Exponential {
+            distr: rand_distr::Exp::new(rate).expect("rate to be positive"),
+            max,
+        }
+    }
+}
+
+impl Default for Exponential {
+    fn default() -> Self {
+        // Choosing 0.5 is not backed by anything more than trial and error.
+        Exponential::new(0.5, Instant(5))
+    }
+}
+
+impl NetworkModel for Exponential {
+    fn delay(&mut self, rng: &mut dyn RngCore, _: &Header, _: Instant) -> Option<Instant> {
+        Some(std::cmp::min(to_instant(self.distr.sample(rng)), self.max))
+    }
+}
+
+/// Delays messages based on a normal distribution. Negative samples are
+/// treated as no delay.
+#[derive(Clone, Copy, Debug)]
+pub struct Normal {
+    distr: rand_distr::Normal<f64>,
+}
+
+impl Normal {
+    pub fn new(mean: f64, std_dev: f64) -> Self {
+        Normal {
+            distr: rand_distr::Normal::new(mean, std_dev).expect("std_dev to be finite"),
+        }
+    }
+}
+
+impl NetworkModel for Normal {
+    fn delay(&mut self, rng: &mut dyn RngCore, _: &Header, _: Instant) -> Option<Instant> {
+        Some(to_instant(self.distr.sample(rng)))
+    }
+}
+
+/// Long-tail delays based on a Pareto distribution, capped at `max`.
+#[derive(Clone, Copy, Debug)]
+pub struct Pareto {
+    distr: rand_distr::Pareto<f64>,
+    max: Instant,
+}
+
+impl Pareto {
+    pub fn new(scale: f64, shape: f64, max: Instant) -> Self {
+        Pareto {
+            distr: rand_distr::Pareto::new(scale, shape).expect("scale and shape to be positive"),
+            max,
+        }
+    }
+}
+
+impl NetworkModel for Pareto {
+    fn delay(&mut self, rng: &mut dyn RngCore, _: &Header, _: Instant) -> Option<Instant> {
+        Some(std::cmp::min(to_instant(self.distr.sample(rng)), self.max))
+    }
+}
+
+/// Delays messages according to `fast` most of the time and according to
+/// `slow` with probability `slow_probability`, e.g. to emulate a network
+/// with occasional congestion.
+#[derive(Clone, Copy, Debug)]
+pub struct Bimodal<F, S> {
+    pub fast: F,
+    pub slow: S,
+    pub slow_probability: f64,
+}
+
+impl<F: NetworkModel, S: NetworkModel> NetworkModel for Bimodal<F, S> {
+    fn delay(&mut self, rng: &mut dyn RngCore, header: &Header, now: Instant) -> Option<Instant> {
+        if rng.gen_bool(self.slow_probability) {
+            self.slow.delay(rng, header, now)
+        } else {
+            self.fast.delay(rng, header, now)
+        }
+    }
+}
+
+fn to_instant(delay: f64) -> Instant {
+    // Casting saturates, thus negative delays end up as 0.
+    Instant(delay as u64)
+}
@@ -0,0 +1,85 @@
+use crate::{Address, Header, Instant};
+use std::collections::BTreeSet;
+
+/// What happens to messages crossing an active partition.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cut {
+    /// Messages are lost.
+    Drop,
+    /// Messages are held back and delivered once the partition heals.
+    Hold,
+}
+
+/// A network partition active from `start` until (excluding) `end`,
+/// separating the nodes in `left` from the nodes in `right`.
+#[derive(Clone, Debug)]
+pub struct Partition {
+    pub start: Instant,
+    pub end: Instant,
+    pub left: BTreeSet<Address>,
+    pub right: BTreeSet<Address>,
+    /// Whether only messages from `left` to `right` are cut, while `right`
+    /// can still reach `left`.
+    pub one_way: bool,
+    pub cut: Cut,
+}
+
+impl Partition {
+    /// Returns a partition dropping all messages between `left` and `right` in
+    /// both directions.
+    pub fn new(start: Instant, end: Instant, left: Vec<Address>, right: Vec<Address>) -> Self {
+        Partition {
+            start,
+            end,
+            left: left.into_iter().collect(),
+            right: right.into_iter().collect(),
+            one_way: false,
+            cut: Cut::Drop,
+        }
+    }
+
+    /// Only cut messages from `left` to `right`.
+    pub fn one_way(mut self) -> Self {
+        self.one_way = true;
+        self
+    }
+
+    /// Hold messages back until the partition heals instead of dropping
+    /// them.
+    pub fn holding(mut self) -> Self {
+        self.cut = Cut::Hold;
+        self
+    }
+
+    pub fn is_active(&self, now: Instant) -> bool {
+        self.start <= now && now < self.end
+    }
+
+    /// Whether the given message, delivered at `now`, crosses the partition.
+    pub fn cuts(&self, header: &Header, now: Instant) -> bool {
+        if !self.is_active(now) {
+            return false;
+        }
+
+        if self.left.contains(&header.from) && self.right.contains(&header.to) {
+            return true;
+        }
+
+        !self.one_way && self.right.contains(&header.from) && self.left.contains(&header.to)
+    }
+}
+
+impl std::fmt::Display for Partition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?}..{:?} {:?} {} {:?} ({:?})",
+            self.start,
+            self.end,
+            self.left,
+            if self.one_way { "-x->" } else { "<-x->" },
+            self.right,
+            self.cut,
+        )
+    }
+}
@@ -0,0 +1,791 @@
+use super::{Client, Crash, Cut, LinkFaults, Monitor, Network, Violation};
+use crate::quorum::{self, QuorumSystem};
+use crate::{
+    Acceptor, Address, Body, Instant, Learner, Msg, Node, Proposer, RequestId, Slot, Timer, Value,
+};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Minimum duration without any message in flight for the simulation to be
+/// done, beyond the proposer timeouts also covering e.g. leader election.
+const MIN_IDLE_TIMEOUT: Instant = Instant(100);
+/// Safety measure to prevent infinite loops, e.g. due to duelling proposers.
+/// The simulation is aborted once no response was sent to an end-user for
+/// this long.
+const MAX_TICKS_WITHOUT_RESPONSE: Instant = Instant(1_000);
+/// Upper bound of the additional delay of a message held back to be
+/// reordered.
+const MAX_REORDER_DELAY: u64 = 10;
+
+/// Counters of messages sent over the emulated network.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub sent: usize,
+    pub delivered: usize,
+    pub dropped: usize,
+    pub duplicated: usize,
+    pub reordered: usize,
+    /// Messages held back by a partition until it healed.
+    pub held: usize,
+}
+
+/// A message delivered by the emulated network.
+#[derive(Clone, Debug)]
+pub struct Delivery<B: Body> {
+    pub msg: Msg<B>,
+    /// Index of the delivery to the sender of `msg` that preceded sending
+    /// `msg`, i.e. the message that most likely triggered it. `None` for
+    /// requests by end-users.
+    pub cause: Option<usize>,
+}
+
+/// A message in flight along with its cause, see [`Delivery::cause`].
+#[derive(Clone, Debug)]
+struct InFlight<B: Body> {
+    msg: Msg<B>,
+    cause: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct Simulator<A: Acceptor<B>, P: Proposer<B>, B: Body, Rng: rand::Rng> {
+    now: Instant,
+    msg_delay_rng: Option<Rng>,
+    network: Network,
+    crashes: Vec<Crash>,
+    /// Nodes currently crashed.
+    down: BTreeSet<Address>,
+    stats: Stats,
+    monitor: Option<Monitor>,
+    /// First invariant violation found by the monitor, stopping the
+    /// simulation.
+    violation: Option<Violation>,
+    /// Quorums of the proposers, checked to intersect before running. Set by
+    /// [`super::Builder::with_quorums`].
+    pub(super) quorums: Box<dyn QuorumSystem>,
+
+    // Ordered maps to keep simulations reproducible given the same random
+    // number generator seed.
+    proposers: BTreeMap<Address, P>,
+    acceptors: BTreeMap<Address, A>,
+    /// Set by [`super::Builder::with_learners`].
+    pub(super) learners: BTreeMap<Address, Box<dyn Learner<B>>>,
+
+    inbox: Vec<InFlight<B>>,
+    /// Messages dispatched to each acceptor within the current tick, handed
+    /// over one at a time, see [`Simulator::step`].
+    acceptor_inbox: BTreeMap<Address, Vec<Msg<B>>>,
+    /// Requests passed to the Simulator beforehand. Later used to ensure
+    /// correctness of the simulation.
+    requests: Vec<Msg<B>>,
+    /// End-users sending the requests, each a simulated endpoint receiving
+    /// responses to its own requests.
+    clients: BTreeSet<Address>,
+    /// End-users sending their requests themselves, retrying on timeout,
+    /// instead of the requests being injected into the inbox. Set by
+    /// [`Simulator::simulate_clients`].
+    simulated_clients: BTreeMap<Address, Client<B>>,
+    responses: Vec<Msg<B>>,
+    /// Requests refused by proposers, see [`Body::is_rejection`].
+    rejections: Vec<Msg<B>>,
+    /// Index into `deliveries` for each response.
+    response_deliveries: Vec<usize>,
+
+    /// All messages delivered so far, used to trace the causal chain leading
+    /// to a response.
+    deliveries: Vec<Delivery<B>>,
+    /// Index into `deliveries` of the last message delivered to each node.
+    last_delivered: BTreeMap<Address, usize>,
+    /// Wake-up each node requested after processing last, see
+    /// [`Node::timer`]. Nodes without an entry are processed right away.
+    timers: BTreeMap<Address, Timer>,
+
+    // The simulator needs to be able to determine when the simulation is done,
+    // thus not making any more progress. One could terminate once no messages
+    // are being transferred anymore. But this would break proposer timeouts.
+    // Instead let's wait a bit longer once there are no more messages.
+    // `last_progress_at` is there to track the above.
+    last_progress_at: Instant,
+    last_response_at: Instant,
+    /// Ten times the longest proposer timeout, giving proposers the chance to
+    /// time out and retry a couple of times, see [`MIN_IDLE_TIMEOUT`].
+    idle_timeout: Instant,
+
+    /// Log lines collected to be printed on failure.
+    pub log: Vec<String>,
+}
+
+impl<A: Acceptor<B>, P: Proposer<B>, B: Body, Rng: rand::Rng> Simulator<A, P, B, Rng> {
+    pub fn new(
+        proposers: BTreeMap<Address, P>,
+        acceptors: BTreeMap<Address, A>,
+        requests: Vec<Msg<B>>,
+        msg_delay_rng: Option<Rng>,
+        network: Network,
+        crashes: Vec<Crash>,
+        mut monitor: Option<Monitor>,
+    ) -> Simulator<A, P, B, Rng> {
+        // Introduces the monitor to all acceptors before any of them
+        // processes a message, see [`Monitor::record`].
+        let violation = monitor.as_mut().and_then(|m| {
+            let states = acceptors
+                .iter()
+                .map(|(address, a)| (address.clone(), a.states()))
+                .collect();
+            m.check::<B>(Instant(0), states, &[]).err()
+        });
+        let acceptor_addresses: Vec<Address> = acceptors.keys().cloned().collect();
+        let idle_timeout = proposers
+            .values()
+            .map(|p| Instant(p.config().max_timeout().0 * 10))
+            .fold(MIN_IDLE_TIMEOUT, Instant::max);
+
+        Simulator {
+            now: Default::default(),
+            msg_delay_rng,
+            network,
+            crashes,
+            down: BTreeSet::new(),
+            stats: Default::default(),
+            monitor,
+            violation,
+            quorums: Default::default(),
+
+            proposers,
+            acceptors,
+            learners: BTreeMap::new(),
+
+            // Init the inbox with the given requests.
+            inbox: requests
+                .iter()
+                .flat_map(|m| B::submit(m.clone(), &acceptor_addresses))
+                .map(|msg| InFlight { msg, cause: None })
+                .collect(),
+            acceptor_inbox: BTreeMap::new(),
+            clients: requests.iter().map(|m| m.header.from.clone()).collect(),
+            requests,
+            simulated_clients: BTreeMap::new(),
+            responses: vec![],
+            rejections: vec![],
+            response_deliveries: vec![],
+
+            deliveries: vec![],
+            last_delivered: BTreeMap::new(),
+            timers: BTreeMap::new(),
+
+            last_progress_at: Default::default(),
+            last_response_at: Default::default(),
+            idle_timeout,
+            log: Default::default(),
+        }
+    }
+
+    /// Has the given clients send the requests instead of injecting them into
+    /// the inbox up front, see [`super::Builder::with_clients`].
+    pub(super) fn simulate_clients(&mut self, clients: BTreeMap<Address, Client<B>>) {
+        // Before the simulation starts the inbox holds nothing but the
+        // requests.
+        self.inbox.clear();
+        self.simulated_clients = clients;
+    }
+
+    /// Step through the simulation until it is done, see
+    /// [`Simulator::is_done`]. Refuses to start with quorums that do not
+    /// intersect, see [`quorum::verify`]. Quorums too large to be verified
+    /// are only warned about in the log.
+    pub fn run(&mut self) -> Result<(), String> {
+        let acceptors: Vec<Address> = self.acceptors.keys().cloned().collect();
+        if quorum::is_verifiable(self.quorums.as_ref(), &acceptors) {
+            quorum::verify(self.quorums.as_ref(), &acceptors)?;
+        } else {
+            self.log.push(format!(
+                "=== Warning | not verifying {:?} with more than {} acceptors",
+                self.quorums,
+                quorum::MAX_VERIFIED_ACCEPTORS
+            ));
+        }
+
+        self.log.push(format!(
+            "=== New simulation | proposers: {} | acceptors: {} | learners: {} | quorums: {:?} | initial inbox: {}",
+            self.proposers.len(),
+            self.acceptors.len(),
+            self.learners.len(),
+            self.quorums,
+            self.inbox.len()
+        ));
+        for p in self.network.partitions.iter() {
+            self.log.push(format!("=== Partition | {}", p));
+        }
+        for c in self.crashes.iter() {
+            self.log.push(format!("=== Crash | {}", c));
+        }
+
+        while !self.is_done() {
+            self.step();
+        }
+
+        self.log.push(format!(
+            "=== Simulation done | now: {:?} | {:?}",
+            self.now, self.stats
+        ));
+
+        match &self.violation {
+            Some(v) => Err(v.to_string()),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether the simulation stopped making progress, exceeded its maximum
+    /// duration or violated an invariant.
+    pub fn is_done(&self) -> bool {
+        self.violation.is_some() || self.is_idle() || self.gave_up()
+    }
+
+    /// Whether the simulation was aborted for not sending any response to an
+    /// end-user for too long while still making progress, e.g. due to
+    /// duelling proposers, see [`MAX_TICKS_WITHOUT_RESPONSE`].
+    pub fn gave_up(&self) -> bool {
+        !self.is_idle() && self.now - self.last_response_at > MAX_TICKS_WITHOUT_RESPONSE
+    }
+
+    fn is_idle(&self) -> bool {
+        // Check if there is any progress. Clients might still send requests
+        // later on.
+        self.inbox.is_empty()
+            && self.now - self.last_progress_at > self.idle_timeout
+            && self.simulated_clients.values().all(|c| c.is_idle())
+    }
+
+    /// Advance the simulation by a single tick, dispatching all due messages
+    /// and having each node process its incoming messages.
+    pub fn step(&mut self) {
+        self.now = self.now + 1;
+        self.log.push(format!("tick {:?}", self.now));
+
+        for p in self.network.partitions.iter() {
+            if p.start == self.now {
+                self.log.push(format!("partition started '{}'", p));
+            }
+            if p.end == self.now {
+                self.log.push(format!("partition healed '{}'", p));
+            }
+        }
+
+        self.crash_and_recover();
+
+        // Dispatch messages.
+        self.inbox.sort_unstable_by(|a, b| a.msg.cmp(&b.msg));
+        let receivers = self.dispatch_msgs();
+
+        // Have entities process messages, skipping the ones neither receiving
+        // any nor due to wake up.
+        let mut new_msgs = vec![];
+        let now = self.now;
+        let (down, timers) = (&self.down, &mut self.timers);
+        let mut process = |address: &Address, node: &mut dyn Node<B>| {
+            let due = timers.get(address).map(|t| t.is_due(now)).unwrap_or(true);
+            if down.contains(address) || !(due || receivers.contains(address)) {
+                return;
+            }
+            new_msgs.append(&mut node.process(now));
+            timers.insert(address.clone(), node.timer());
+        };
+        for (address, c) in self.simulated_clients.iter_mut() {
+            process(address, c);
+        }
+        for (address, p) in self.proposers.iter_mut() {
+            process(address, p);
+        }
+        // Acceptors process one message at a time, letting the monitor
+        // record each value accepted, even one overwritten within the same
+        // tick.
+        for (address, a) in self.acceptors.iter_mut() {
+            let msgs = self.acceptor_inbox.remove(address).unwrap_or_default();
+            if msgs.is_empty() {
+                process(address, a);
+            }
+            for m in msgs {
+                a.receive(m);
+                process(address, a);
+
+                let monitor = match self.monitor.as_mut() {
+                    Some(monitor) if self.violation.is_none() => monitor,
+                    _ => continue,
+                };
+                if let Err(v) = monitor.record::<B>(now, address.clone(), a.states()) {
+                    self.log.push(format!("invariant violated '{}'", v));
+                    self.violation = Some(v);
+                }
+            }
+        }
+        for (address, l) in self.learners.iter_mut() {
+            process(address, l.as_mut());
+        }
+
+        // Simulated clients send nothing but requests, each submitted as the
+        // protocol asks for, see [`Body::submit`].
+        let acceptors: Vec<Address> = self.acceptors.keys().cloned().collect();
+        let new_msgs: Vec<Msg<B>> = new_msgs
+            .into_iter()
+            .flat_map(|m| {
+                if self.simulated_clients.contains_key(&m.header.from) {
+                    B::submit(m, &acceptors)
+                } else {
+                    vec![m]
+                }
+            })
+            .collect();
+
+        if let (Some(monitor), None) = (self.monitor.as_mut(), self.violation.as_ref()) {
+            let states = self
+                .acceptors
+                .iter()
+                .map(|(address, a)| (address.clone(), a.states()))
+                .collect();
+            if let Err(v) = monitor.check(self.now, states, &new_msgs) {
+                self.log.push(format!("invariant violated '{}'", v));
+                self.violation = Some(v);
+            }
+        }
+
+        // Producing new messages is equal to overall progress.
+        if !new_msgs.is_empty() {
+            self.last_progress_at = self.now;
+        }
+
+        for m in new_msgs {
+            let cause = self.last_delivered.get(&m.header.from).cloned();
+            self.send(m, cause);
+        }
+    }
+
+    /// Hands the given message to the emulated network. Messages are delayed
+    /// and subject to faults only if a random number generator is set.
+    fn send(&mut self, mut m: Msg<B>, cause: Option<usize>) {
+        self.stats.sent += 1;
+
+        if self.msg_delay_rng.is_none() {
+            self.inbox.push(InFlight { msg: m, cause });
+            return;
+        }
+
+        let faults = if self.is_end_user(&m.header.from) || self.is_end_user(&m.header.to) {
+            LinkFaults::default()
+        } else {
+            self.network.faults.link(&m.header.from, &m.header.to)
+        };
+        let rng = self.msg_delay_rng.as_mut().unwrap();
+
+        if rng.gen_bool(faults.drop) {
+            self.stats.dropped += 1;
+            self.log.push(format!("dropping msg '{:?}'", m));
+            return;
+        }
+
+        let copies = if rng.gen_bool(faults.duplicate) {
+            self.stats.duplicated += 1;
+            self.log.push(format!("duplicating msg '{:?}'", m));
+            2
+        } else {
+            1
+        };
+
+        let sent_at = m.header.at;
+        for _ in 0..copies {
+            let mut delay = match self.network.model.delay(rng, &m.header, self.now) {
+                Some(delay) => delay,
+                None => {
+                    self.stats.dropped += 1;
+                    self.log.push(format!("dropping msg '{:?}'", m));
+                    continue;
+                }
+            };
+
+            if rng.gen_bool(faults.reorder) {
+                self.stats.reordered += 1;
+                delay = delay + rng.gen_range(1, MAX_REORDER_DELAY + 1);
+            }
+
+            m.header.at = sent_at + delay;
+            self.inbox.push(InFlight {
+                msg: m.clone(),
+                cause,
+            });
+        }
+    }
+
+    /// Crashes and recovers nodes according to the crash schedule.
+    fn crash_and_recover(&mut self) {
+        for c in self.crashes.iter() {
+            if c.at == self.now {
+                self.log.push(format!("crashing '{}'", c));
+                self.down.insert(c.node.clone());
+                // The node lost its pending timer along with its volatile
+                // state.
+                self.timers.remove(&c.node);
+
+                match self.proposers.get_mut(&c.node) {
+                    Some(_) if c.amnesia => {
+                        panic!(
+                            "{:?} is a proposer, amnesia is only supported for acceptors",
+                            c.node
+                        )
+                    }
+                    Some(p) => p.crash(self.now),
+                    None => match self.acceptors.get_mut(&c.node) {
+                        Some(a) if c.amnesia => a.amnesia(self.now),
+                        Some(a) => a.crash(self.now),
+                        None => match self.learners.get_mut(&c.node) {
+                            Some(l) => l.crash(self.now),
+                            None => match self.simulated_clients.get_mut(&c.node) {
+                                Some(client) => client.crash(self.now),
+                                None => panic!("{:?} is not a known node", c.node),
+                            },
+                        },
+                    },
+                }
+            }
+
+            if c.recover_at == Some(self.now) {
+                self.log.push(format!("recovering '{}'", c));
+                self.down.remove(&c.node);
+            }
+        }
+    }
+
+    fn is_end_user(&self, a: &Address) -> bool {
+        self.clients.contains(a)
+    }
+
+    /// Delivers all messages due by now, returning the nodes they were
+    /// delivered to.
+    fn dispatch_msgs(&mut self) -> BTreeSet<Address> {
+        let mut receivers = BTreeSet::new();
+        while self
+            .inbox
+            .first()
+            .map(|f| f.msg.header.at <= self.now)
+            .unwrap_or(false)
+        {
+            let InFlight { msg: mut m, cause } = self.inbox.remove(0);
+
+            if let Some(p) = self
+                .network
+                .partitions
+                .iter()
+                .find(|p| p.cuts(&m.header, self.now))
+            {
+                match p.cut {
+                    Cut::Drop => {
+                        self.stats.dropped += 1;
+                        self.log
+                            .push(format!("dropping msg '{:?}' due to partition", m));
+                    }
+                    Cut::Hold => {
+                        self.stats.held += 1;
+                        self.log
+                            .push(format!("holding msg '{:?}' due to partition", m));
+                        m.header.at = p.end;
+                        // Delivered at a later instant, thus pushing it to the
+                        // end does not interfere with the loop.
+                        self.inbox.push(InFlight { msg: m, cause });
+                    }
+                }
+                continue;
+            }
+
+            if self.down.contains(&m.header.to) {
+                self.stats.dropped += 1;
+                self.log
+                    .push(format!("dropping msg '{:?}' to crashed node", m));
+                continue;
+            }
+
+            self.stats.delivered += 1;
+            self.log.push(format!("dispatching msg '{:?}'", m));
+            receivers.insert(m.header.to.clone());
+            self.dispatch_msg(m, cause);
+        }
+
+        receivers
+    }
+
+    fn dispatch_msg(&mut self, m: Msg<B>, cause: Option<usize>) {
+        let id = self.deliveries.len();
+        self.deliveries.push(Delivery {
+            msg: m.clone(),
+            cause,
+        });
+        self.last_delivered.insert(m.header.to.clone(), id);
+
+        if self.clients.contains(&m.header.to) {
+            self.last_response_at = self.now;
+            if let Some(c) = self.simulated_clients.get_mut(&m.header.to) {
+                c.receive(m.clone());
+            }
+            if m.body.is_rejection().is_some() {
+                self.rejections.push(m);
+                return;
+            }
+            self.responses.push(m);
+            self.response_deliveries.push(id);
+            return;
+        }
+
+        match self.proposers.get_mut(&m.header.to) {
+            Some(p) => p.receive(m),
+            None if self.acceptors.contains_key(&m.header.to) => self
+                .acceptor_inbox
+                .entry(m.header.to.clone())
+                .or_default()
+                .push(m),
+            None => match self.learners.get_mut(&m.header.to) {
+                Some(l) => l.receive(m),
+                None => panic!("{:?} is not a known node", m.header.to),
+            },
+        }
+    }
+
+    /// Ensure that the past simulation is within the consistency guarantees we
+    /// would like to achieve.
+    ///
+    /// Definition of consensus
+    ///
+    /// - All non-faulty processes eventually decide on a value.
+    ///
+    /// - All processes decide on the same value.
+    ///
+    /// - The decided value was intitially proposed.
+    ///
+    /// Protocols replicating a log are held to the above for each slot, see
+    /// [`Body::slot`]. Values learned by learners need to match the responses.
+    /// Each request needs exactly one answer, sent to the client that
+    /// requested it, unless rejected. A resubmitted request, see [`RequestId`], might be
+    /// responded to more than once, though always with the same slot and
+    /// value.
+    pub fn ensure_correctness(&self) -> Result<(), String> {
+        self.ensure_safety()?;
+
+        // Requests refused by a busy proposer need no answer, unless
+        // resubmitted successfully.
+        let answers = self.answers()?;
+        let rejected: BTreeSet<RequestId> = self
+            .rejections
+            .iter()
+            .filter_map(|m| m.body.is_rejection())
+            .filter(|id| !answers.contains_key(id))
+            .collect();
+        let requests = self
+            .requests
+            .iter()
+            .filter_map(|r| r.body.is_request())
+            .map(|(id, _)| id)
+            .collect::<BTreeSet<_>>()
+            .len();
+        if answers.len() + rejected.len() != requests {
+            return Err(format!(
+                "expected {} responses, got {} responses",
+                requests - rejected.len(),
+                answers.len(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Like [`Simulator::ensure_correctness`], without requiring every
+    /// request to be answered, e.g. when the network loses messages or the
+    /// proposers are known to livelock. Whatever was decided still needs to
+    /// be consistent.
+    pub fn ensure_safety(&self) -> Result<(), String> {
+        let answers = self.answers()?;
+        for (id, answers) in answers.iter() {
+            if answers.len() > 1 {
+                return Err(format!(
+                    "expected one answer to {:?}, got {:?}",
+                    id, answers
+                ));
+            }
+        }
+
+        let mut final_values: BTreeMap<Slot, Vec<Value>> = BTreeMap::new();
+        for r in self.responses.iter() {
+            match r.body.is_response() {
+                Some((_, v)) => final_values
+                    .entry(r.body.slot())
+                    .or_default()
+                    .push(v.clone()),
+                _ => unreachable!(),
+            }
+        }
+
+        for (slot, final_values) in final_values.iter() {
+            let mut unique_final_values = final_values.clone();
+            unique_final_values.sort_unstable();
+            unique_final_values.dedup();
+
+            if unique_final_values.len() > 1 {
+                let mut err = format!("got more than one final result: '{:?}'", final_values);
+                if *slot != Slot(0) {
+                    err.push_str(&format!(" in {:?}", slot));
+                }
+
+                // Explain how each of the values came to be decided.
+                for v in unique_final_values.iter() {
+                    let i = self
+                        .responses
+                        .iter()
+                        .position(|r| {
+                            r.body.slot() == *slot
+                                && r.body.is_response().map(|(_, r)| r).as_ref() == Some(v)
+                        })
+                        .unwrap();
+                    err.push_str(&format!("\ncausal chain of {:?}:", v));
+                    for d in self.causal_chain(self.response_deliveries[i]) {
+                        err.push_str(&format!("\n  {:?}", d.msg));
+                    }
+                }
+                for c in self.crashes.iter() {
+                    err.push_str(&format!("\ncrash {}", c));
+                }
+
+                return Err(err);
+            }
+        }
+
+        let requested_values: Vec<Value> = self
+            .requests
+            .iter()
+            .map(|req| match req.body.is_request() {
+                Some((_, v)) => v,
+                _ => unreachable!(),
+            })
+            .collect();
+
+        for final_value in final_values.values().flatten() {
+            if !requested_values.contains(final_value) {
+                return Err(format!(
+                    "expected decided value to be among the initially proposed
+                    values, got value \"{:?}\", initial requests \"{:?}\"",
+                    final_value, self.requests,
+                ));
+            }
+        }
+
+        // Learners might miss out on a decision, e.g. due to a dropped
+        // notification, but never learn a different value.
+        for (address, l) in self.learners.iter() {
+            for (slot, learned) in l.learned() {
+                match final_values.get(&slot) {
+                    Some(values) if values.contains(&learned) => {}
+                    _ => {
+                        return Err(format!(
+                            "learner {:?} learned {:?} in {:?}, decided were {:?}",
+                            address,
+                            learned,
+                            slot,
+                            final_values.get(&slot)
+                        ))
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the answers to each request, ensuring each went to the client
+    /// that sent the request.
+    fn answers(&self) -> Result<BTreeMap<RequestId, BTreeSet<(Slot, Value)>>, String> {
+        let mut clients: BTreeMap<RequestId, &Address> = BTreeMap::new();
+        for r in self.requests.iter() {
+            let (id, _) = r.body.is_request().expect("requests to be requests");
+            clients.insert(id, &r.header.from);
+        }
+
+        let mut answers: BTreeMap<RequestId, BTreeSet<(Slot, Value)>> = BTreeMap::new();
+        for r in self.responses.iter() {
+            let (id, v) = r.body.is_response().expect("responses to be responses");
+            match clients.get(&id) {
+                Some(client) if **client == r.header.to => {}
+                Some(client) => {
+                    return Err(format!(
+                        "expected response to {:?} to go to {:?}, got '{:?}'",
+                        id, client, r
+                    ))
+                }
+                None => return Err(format!("got response to unknown request '{:?}'", r)),
+            }
+            answers.entry(id).or_default().insert((r.body.slot(), v));
+        }
+
+        Ok(answers)
+    }
+
+    /// Returns the chain of deliveries leading up to and including the given
+    /// delivery, oldest first, following [`Delivery::cause`].
+    pub fn causal_chain(&self, delivery: usize) -> Vec<&Delivery<B>> {
+        let mut chain = vec![];
+        let mut next = Some(delivery);
+        while let Some(i) = next {
+            chain.push(&self.deliveries[i]);
+            next = self.deliveries[i].cause;
+        }
+        chain.reverse();
+        chain
+    }
+
+    pub fn get_now(&self) -> Instant {
+        self.now
+    }
+
+    /// Requests injected into the simulation at construction.
+    pub fn requests(&self) -> &[Msg<B>] {
+        &self.requests
+    }
+
+    /// End-users that sent the requests.
+    pub fn clients(&self) -> &BTreeSet<Address> {
+        &self.clients
+    }
+
+    /// Clients sending requests themselves, see
+    /// [`super::Builder::with_clients`].
+    pub fn simulated_clients(&self) -> &BTreeMap<Address, Client<B>> {
+        &self.simulated_clients
+    }
+
+    /// Responses sent by proposers to end-users so far.
+    pub fn responses(&self) -> &[Msg<B>] {
+        &self.responses
+    }
+
+    /// Rejections sent by proposers to end-users so far.
+    pub fn rejections(&self) -> &[Msg<B>] {
+        &self.rejections
+    }
+
+    /// Messages delivered so far, see [`Simulator::causal_chain`].
+    pub fn deliveries(&self) -> &[Delivery<B>] {
+        &self.deliveries
+    }
+
+    pub fn proposers(&self) -> &BTreeMap<Address, P> {
+        &self.proposers
+    }
+
+    pub fn acceptors(&self) -> &BTreeMap<Address, A> {
+        &self.acceptors
+    }
+
+    pub fn learners(&self) -> &BTreeMap<Address, Box<dyn Learner<B>>> {
+        &self.learners
+    }
+
+    /// Returns the invariant violation that stopped the simulation, if any.
+    pub fn violation(&self) -> Option<&Violation> {
+        self.violation.as_ref()
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+}
@@ -0,0 +1,112 @@
+use crate::storage::{AcceptorState, Storage};
+use crate::Instant;
+use std::collections::VecDeque;
+
+/// In-memory storage emulating a disk with write latency and faults.
+///
+/// A write is durable `write_latency` after it was issued. Writes are
+/// durable in the order they were issued, each at its own instant, thus a
+/// write still in flight does not hold back the ones issued before it.
+#[derive(Clone, Debug)]
+pub struct SimulatedStorage<Rng: rand::Rng, S = AcceptorState> {
+    rng: Rng,
+    write_latency: Instant,
+    /// Probability of a write failing.
+    failure_probability: f64,
+    /// Whether a crash loses the writes still in flight.
+    lose_unsynced_on_crash: bool,
+
+    durable: S,
+    /// Writes not yet durable along with the instant each is durable at,
+    /// oldest first.
+    pending: VecDeque<(Instant, S)>,
+}
+
+impl<Rng: rand::Rng, S: Default> SimulatedStorage<Rng, S> {
+    pub fn new(rng: Rng) -> Self {
+        SimulatedStorage {
+            rng,
+            write_latency: Instant(0),
+            failure_probability: 0.0,
+            lose_unsynced_on_crash: false,
+            durable: S::default(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub fn with_write_latency(mut self, latency: Instant) -> Self {
+        self.write_latency = latency;
+        self
+    }
+
+    pub fn with_failure_probability(mut self, p: f64) -> Self {
+        self.failure_probability = p;
+        self
+    }
+
+    /// Lose the writes still in flight on crash, e.g. due to a volatile disk
+    /// cache.
+    pub fn losing_unsynced_on_crash(mut self) -> Self {
+        self.lose_unsynced_on_crash = true;
+        self
+    }
+
+    /// Makes the writes durable by the given instant.
+    fn sync(&mut self, now: Instant) {
+        while let Some((durable_at, _)) = self.pending.front() {
+            if *durable_at > now {
+                break;
+            }
+            let (_, state) = self.pending.pop_front().expect("a pending write");
+            self.durable = state;
+        }
+    }
+}
+
+impl<Rng, S> Storage<S> for SimulatedStorage<Rng, S>
+where
+    Rng: rand::Rng + Clone + std::fmt::Debug + 'static,
+    S: Clone + Default + std::fmt::Debug + 'static,
+{
+    fn write(&mut self, state: &S, now: Instant) -> std::io::Result<Instant> {
+        if self.rng.gen_bool(self.failure_probability) {
+            return Err(std::io::Error::other("simulated write failure"));
+        }
+
+        self.sync(now);
+
+        let durable_at = now + self.write_latency;
+        self.pending.push_back((durable_at, state.clone()));
+
+        Ok(durable_at)
+    }
+
+    fn read(&self) -> std::io::Result<S> {
+        match self.pending.back() {
+            Some((_, state)) => Ok(state.clone()),
+            None => Ok(self.durable.clone()),
+        }
+    }
+
+    fn crash(&mut self, now: Instant) {
+        self.sync(now);
+
+        // Only the writes still pending are lost, if at all.
+        let pending = std::mem::take(&mut self.pending);
+        if !self.lose_unsynced_on_crash {
+            if let Some((_, state)) = pending.into_iter().last() {
+                self.durable = state;
+            }
+        }
+    }
+
+    fn wipe(&mut self) -> std::io::Result<()> {
+        self.durable = S::default();
+        self.pending.clear();
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn Storage<S>> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,158 @@
+//! Stable storage used by acceptors to persist their state across crashes.
+
+use crate::{Epoch, Instant, Value};
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+/// State an acceptor needs to persist before replying to a proposer in order
+/// to stay safe across crashes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AcceptorState {
+    pub promised_epoch: Option<Epoch>,
+    pub accepted: Option<(Epoch, Value)>,
+}
+
+/// Persists the state of an acceptor, by default the one of a single
+/// instance, see [`AcceptorState`].
+pub trait Storage<S = AcceptorState>: std::fmt::Debug {
+    /// Write persists the given state, returning the instant at which the
+    /// write is durable.
+    fn write(&mut self, state: &S, now: Instant) -> std::io::Result<Instant>;
+    /// Read returns the latest written state.
+    fn read(&self) -> std::io::Result<S>;
+    /// Crash notifies the storage about a crash of its node at `now`, e.g. to
+    /// lose writes that are not yet durable.
+    fn crash(&mut self, _now: Instant) {}
+    /// Wipe loses all state, written or durable, e.g. due to a replaced disk.
+    fn wipe(&mut self) -> std::io::Result<()>;
+    /// Returns a copy of the storage, e.g. to explore different continuations
+    /// of a simulation. Copies of a [`FileStorage`] share the same file.
+    fn box_clone(&self) -> Box<dyn Storage<S>>;
+}
+
+impl<S> Clone for Box<dyn Storage<S>> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+/// Storage keeping the state in memory, with every write being durable right
+/// away and surviving crashes.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStorage<S = AcceptorState> {
+    state: S,
+}
+
+impl<S: Clone + Default + std::fmt::Debug + 'static> Storage<S> for MemoryStorage<S> {
+    fn write(&mut self, state: &S, now: Instant) -> std::io::Result<Instant> {
+        self.state = state.clone();
+        Ok(now)
+    }
+
+    fn read(&self) -> std::io::Result<S> {
+        Ok(self.state.clone())
+    }
+
+    fn wipe(&mut self) -> std::io::Result<()> {
+        self.state = S::default();
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn Storage<S>> {
+        Box::new(self.clone())
+    }
+}
+
+/// Storage persisting the state to a file, syncing it to disk on every write.
+#[derive(Clone, Debug)]
+pub struct FileStorage {
+    path: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(path: PathBuf) -> Self {
+        FileStorage { path }
+    }
+}
+
+impl Storage for FileStorage {
+    fn write(&mut self, state: &AcceptorState, now: Instant) -> std::io::Result<Instant> {
+        if let Some((_, v)) = &state.accepted {
+            if v.0.contains('\n') {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "value contains a newline",
+                ));
+            }
+        }
+
+        // Write to a temporary file first and rename it afterwards, thus never
+        // leaving a torn file behind.
+        let tmp_path = self.path.with_extension("tmp");
+        let mut file = std::fs::File::create(&tmp_path)?;
+
+        if let Some(e) = state.promised_epoch {
+            writeln!(file, "promised {} {}", e.epoch, e.identifier)?;
+        }
+        if let Some((e, v)) = &state.accepted {
+            writeln!(file, "accepted {} {} {}", e.epoch, e.identifier, v.0)?;
+        }
+
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, &self.path)?;
+
+        Ok(now)
+    }
+
+    fn read(&self) -> std::io::Result<AcceptorState> {
+        let file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(AcceptorState::default())
+            }
+            Err(e) => return Err(e),
+        };
+
+        let mut state = AcceptorState::default();
+        for line in std::io::BufReader::new(file).lines() {
+            let line = line?;
+            let mut fields = line.splitn(4, ' ');
+            let kind = fields.next();
+            let epoch = Epoch::new(parse_field(fields.next())?, parse_field(fields.next())?);
+
+            match kind {
+                Some("promised") => state.promised_epoch = Some(epoch),
+                Some("accepted") => {
+                    state.accepted = Some((epoch, Value::new(fields.next().unwrap_or(""))))
+                }
+                _ => return Err(invalid_data(&line)),
+            }
+        }
+
+        Ok(state)
+    }
+
+    fn wipe(&mut self) -> std::io::Result<()> {
+        match std::fs::remove_file(&self.path) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            r => r,
+        }
+    }
+
+    fn box_clone(&self) -> Box<dyn Storage> {
+        Box::new(self.clone())
+    }
+}
+
+fn parse_field(field: Option<&str>) -> std::io::Result<u32> {
+    field
+        .and_then(|f| f.parse().ok())
+        .ok_or_else(|| invalid_data(field.unwrap_or("")))
+}
+
+fn invalid_data(s: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("failed to parse '{}'", s),
+    )
+}
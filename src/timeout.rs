@@ -0,0 +1,239 @@
+//! Timeouts after which a proposer gives up waiting for acceptors and starts
+//! over with a higher epoch.
+//!
+//! A fixed timeout has to be tuned to the network: too short and proposers
+//! keep preempting each other while messages are merely slow, too long and
+//! a lost message stalls a request. An adaptive timeout instead follows the
+//! round-trip times a proposer observes between sending prepares or
+//! proposals and receiving the promises or accepts, estimated the way TCP
+//! estimates its retransmission timeout. Like TCP it doubles the timeout
+//! each time it expires, otherwise never observing a round trip longer than
+//! the initial timeout.
+//!
+//! Either way proposers sharing the same timeout tend to time out together,
+//! preempting each other's prepares over and over. As in Raft's leader
+//! election, a randomized timeout adds a random duration drawn anew after
+//! each expiry, letting one of the proposers get ahead.
+
+use crate::Instant;
+
+/// Timeouts of a proposer, see [`crate::Proposer::set_config`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProposerConfig {
+    /// How long to wait for progress before starting over, the initial
+    /// timeout if adaptive.
+    pub timeout: Instant,
+    /// Bounds of a timeout following the observed round-trip times, fixed
+    /// if none.
+    pub adaptive: Option<Adaptive>,
+    /// Range of the random duration added to the timeout, none if `None`.
+    pub randomized: Option<Randomized>,
+}
+
+impl Default for ProposerConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Instant(10),
+            adaptive: None,
+            randomized: None,
+        }
+    }
+}
+
+impl ProposerConfig {
+    /// Starts with the given timeout, adapting it to the observed round-trip
+    /// times within the given bounds.
+    pub fn adaptive(timeout: Instant, min: Instant, max: Instant) -> Self {
+        Self {
+            timeout,
+            adaptive: Some(Adaptive { min, max }),
+            randomized: None,
+        }
+    }
+
+    /// Draws each timeout uniformly from `min..=max`, using a random number
+    /// generator seeded with the given seed and the identifier of the
+    /// proposer.
+    pub fn randomized(min: Instant, max: Instant, seed: u64) -> Self {
+        Self {
+            timeout: min,
+            adaptive: None,
+            randomized: Some(Randomized {
+                spread: Instant(max.0.saturating_sub(min.0)),
+                seed,
+            }),
+        }
+    }
+
+    /// Returns the longest timeout the proposer might wait for, e.g. to tell
+    /// a stalled simulation from a waiting proposer.
+    pub fn max_timeout(&self) -> Instant {
+        let timeout = match self.adaptive {
+            Some(Adaptive { max, .. }) => max.max(self.timeout),
+            None => self.timeout,
+        };
+        match self.randomized {
+            Some(Randomized { spread, .. }) => timeout + spread,
+            None => timeout,
+        }
+    }
+}
+
+/// Bounds of an adaptive timeout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Adaptive {
+    pub min: Instant,
+    pub max: Instant,
+}
+
+/// Random duration added to a timeout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Randomized {
+    /// Upper bound of the random duration, inclusive.
+    pub spread: Instant,
+    pub seed: u64,
+}
+
+/// Current timeout of a proposer according to its [`ProposerConfig`].
+#[derive(Clone, Debug)]
+pub struct Timeout {
+    config: ProposerConfig,
+    /// Mixed into the seed of randomized timeouts, e.g. the identifier of
+    /// the proposer, thus proposers with the same config differing.
+    identifier: u64,
+    rng: Jitter,
+    /// Random duration added to the timeout, drawn anew after each expiry.
+    random: Instant,
+    /// Smoothed round-trip time scaled by 8 and its mean deviation scaled by
+    /// 4, once a round trip was observed.
+    estimate: Option<(u64, u64)>,
+    /// Number of times the timeout expired since the last round trip.
+    expired: u32,
+}
+
+impl Timeout {
+    pub fn new(config: ProposerConfig, identifier: u64) -> Self {
+        let seed = config.randomized.map(|r| r.seed).unwrap_or(0);
+        let mut timeout = Self {
+            config,
+            identifier,
+            rng: Jitter::new(seed ^ identifier),
+            random: Instant(0),
+            estimate: None,
+            expired: 0,
+        };
+        timeout.draw();
+        timeout
+    }
+
+    pub fn config(&self) -> ProposerConfig {
+        self.config
+    }
+
+    /// Switches to the given config, starting over.
+    pub fn set_config(&mut self, config: ProposerConfig) {
+        *self = Timeout::new(config, self.identifier);
+    }
+
+    /// Forgets the observed round trips and expiries, e.g. after a crash,
+    /// while the random number generator moves on.
+    pub fn restart(&mut self) {
+        self.estimate = None;
+        self.expired = 0;
+        self.draw();
+    }
+
+    /// Records the round-trip time of a prepare or a proposal, ignored
+    /// unless adaptive.
+    pub fn observe(&mut self, rtt: Instant) {
+        if self.config.adaptive.is_none() {
+            return;
+        }
+
+        self.expired = 0;
+        let rtt = rtt.0;
+        self.estimate = Some(match self.estimate {
+            None => (rtt << 3, rtt << 1),
+            // The smoothed round-trip time moves by 1/8 of the error, the
+            // deviation by 1/4, see RFC 6298.
+            Some((srtt, rttvar)) => {
+                let error = rtt as i64 - (srtt >> 3) as i64;
+                let srtt = (srtt as i64 + error).max(0) as u64;
+                let rttvar = rttvar + error.unsigned_abs() - (rttvar >> 2);
+                (srtt, rttvar)
+            }
+        });
+    }
+
+    /// Records that the timeout expired, doubling it until the next round
+    /// trip is observed unless fixed, and drawing a new random duration.
+    pub fn expire(&mut self) {
+        if self.config.adaptive.is_some() {
+            self.expired = self.expired.saturating_add(1);
+        }
+        self.draw();
+    }
+
+    fn draw(&mut self) {
+        if let Some(Randomized { spread, .. }) = self.config.randomized {
+            self.random = Instant(self.rng.below(spread.0.saturating_add(1)));
+        }
+    }
+
+    /// Returns how long to wait for progress. Once adaptive, that is the
+    /// smoothed round-trip time plus four times its deviation, doubled for
+    /// each expiry since. The random duration, if any, comes on top.
+    pub fn get(&self) -> Instant {
+        self.base() + self.random
+    }
+
+    fn base(&self) -> Instant {
+        let Adaptive { min, max } = match self.config.adaptive {
+            Some(adaptive) => adaptive,
+            None => return self.config.timeout,
+        };
+
+        let timeout = match self.estimate {
+            Some((srtt, rttvar)) => (srtt >> 3) + rttvar,
+            None => self.config.timeout.0,
+        };
+        let timeout = timeout.saturating_mul(1u64 << self.expired.min(63));
+        Instant(timeout.clamp(min.0, max.0.max(min.0)))
+    }
+
+    /// Returns whether a proposer that last made progress at the given
+    /// instant timed out by now.
+    pub fn is_expired(&self, last_progress_at: Instant, now: Instant) -> bool {
+        now >= last_progress_at + self.get()
+    }
+}
+
+/// Pseudo random number generator for randomized timeouts and
+/// [`crate::nack::RetryPolicy::Exponential`], keeping simulations
+/// reproducible given the same seed. Implements SplitMix64, the protocol core
+/// not depending on any crate.
+#[derive(Clone, Debug)]
+pub struct Jitter(u64);
+
+impl Jitter {
+    pub fn new(seed: u64) -> Self {
+        Jitter(seed)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `0..n`, zero if `n` is zero.
+    pub(crate) fn below(&mut self, n: u64) -> u64 {
+        if n == 0 {
+            return 0;
+        }
+
+        self.next() % n
+    }
+}
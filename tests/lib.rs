@@ -1,34 +1,38 @@
+use paxos_simulator::quorum::{self, Grid, Hierarchical, QuorumSystem, Quorums, Weighted};
+use paxos_simulator::sim::network;
 use paxos_simulator::{
-    classic, nack, Acceptor, Address, Body, Epoch, Header, Instant, Msg, Proposer, Value,
+    classic, fast, multi, nack, sim, Address, Body, Epoch, Instant, Learning, RequestId, Slot,
+    Value,
 };
 use quickcheck::TestResult;
 use rand::Rng;
 use rand::{rngs::StdRng, SeedableRng};
-use std::collections::HashMap;
-
-pub mod simulator;
 
 #[macro_use(quickcheck)]
 extern crate quickcheck_macros;
 
 #[test]
 fn single_proposer_three_acceptors_one_request() {
-    let mut s = ClassicPaxosBuilder::<StdRng>::new()
-        .with_proposers(1)
-        .with_accpetors(3)
-        .with_requests(vec![(1, 0)])
-        .build();
+    let mut s =
+        sim::Builder::<_, _, _, StdRng>::new(classic::Proposer::new, classic::Acceptor::new)
+            .with_proposers(1)
+            .with_acceptors(3)
+            .with_requests(vec![(1, 0)])
+            .build()
+            .unwrap();
     s.run().unwrap();
     s.ensure_correctness().unwrap();
 }
 
 #[test]
 fn two_proposer_three_acceptors_two_request() {
-    let mut s = ClassicPaxosBuilder::<StdRng>::new()
-        .with_proposers(2)
-        .with_accpetors(3)
-        .with_requests(vec![(1, 0), (2, 1)])
-        .build();
+    let mut s =
+        sim::Builder::<_, _, _, StdRng>::new(classic::Proposer::new, classic::Acceptor::new)
+            .with_proposers(2)
+            .with_acceptors(3)
+            .with_requests(vec![(1, 0), (2, 1)])
+            .build()
+            .unwrap();
     s.run().unwrap();
     s.ensure_correctness().unwrap();
 }
@@ -44,12 +48,13 @@ fn regression_1() {
         .map(|i| (*i, rng.gen_range(0, 1)))
         .collect();
 
-    let mut s = ClassicPaxosBuilder::<StdRng>::new()
+    let mut s = sim::Builder::new(classic::Proposer::new, classic::Acceptor::new)
         .with_proposers(1)
-        .with_accpetors(3)
+        .with_acceptors(3)
         .with_requests(requests)
         .with_msg_delay_rng(rng)
-        .build();
+        .build()
+        .unwrap();
     s.run().unwrap();
 
     match s.ensure_correctness() {
@@ -58,268 +63,1894 @@ fn regression_1() {
             for l in s.log.iter() {
                 println!("{}", l);
             }
-            panic!(e);
+            panic!("{}", e);
         }
     }
 }
 
-// TODO: Find a way to abstract over classic and nack to not need to duplicate
-// the code below.
-#[quickcheck]
-fn variable_requests(
-    proposers: u32,
-    acceptors: u32,
-    request_instants: Vec<u64>,
-    seed: u64,
-) -> TestResult {
-    if proposers == 0 || acceptors == 0 {
-        return TestResult::discard();
-    }
+#[test]
+fn network_models() {
+    let models: Vec<Box<dyn sim::NetworkModel>> = vec![
+        Box::new(network::Constant(Instant(3))),
+        Box::new(network::Uniform::new(Instant(1), Instant(8))),
+        Box::new(network::Exponential::default()),
+        Box::new(network::Normal::new(4.0, 2.0)),
+        Box::new(network::Pareto::new(1.0, 1.5, Instant(50))),
+        Box::new(network::Bimodal {
+            fast: network::Constant(Instant(1)),
+            slow: network::Uniform::new(Instant(20), Instant(40)),
+            slow_probability: 0.1,
+        }),
+    ];
 
-    if proposers > 10 || acceptors > 10 || request_instants.len() > 100 {
-        return TestResult::discard();
+    for model in models {
+        let mut s = sim::Builder::new(classic::Proposer::new, classic::Acceptor::new)
+            .with_proposers(2)
+            .with_acceptors(3)
+            .with_requests(vec![(1, 0), (2, 1), (30, 0)])
+            .with_msg_delay_rng(StdRng::seed_from_u64(0))
+            .with_network_model(model)
+            .build()
+            .unwrap();
+        s.run().unwrap();
+        s.ensure_correctness().unwrap();
     }
 
-    let mut rng = StdRng::seed_from_u64(seed);
+    // Without a random number generator the model would not be consulted.
+    let s = sim::Builder::<_, _, _, StdRng>::new(classic::Proposer::new, classic::Acceptor::new)
+        .with_proposers(1)
+        .with_acceptors(3)
+        .with_requests(vec![(1, 0)])
+        .with_network_model(network::Constant(Instant(3)))
+        .build();
+    assert!(s.is_err());
+}
 
-    let requests = request_instants
-        .iter()
-        .map(|i| (*i, rng.gen_range(0, proposers)))
-        .collect();
+#[test]
+#[should_panic]
+fn uniform_bounds() {
+    network::Uniform::new(Instant(2), Instant(1));
+}
 
-    let mut simulator = ClassicPaxosBuilder::<StdRng>::new()
-        .with_proposers(proposers)
-        .with_accpetors(acceptors)
-        .with_requests(requests)
-        .with_msg_delay_rng(rng)
-        .build();
+#[test]
+fn partitions() {
+    let a = Address::new;
 
-    simulator.run().unwrap();
+    // The proposer can not reach a majority of acceptors until tick 100, the
+    // acceptors can still reach the proposer though. Afterwards the promise of
+    // a1 is held back until tick 150.
+    let mut s = sim::Builder::new(classic::Proposer::new, classic::Acceptor::new)
+        .with_proposers(1)
+        .with_acceptors(3)
+        .with_requests(vec![(1, 0)])
+        .with_msg_delay_rng(StdRng::seed_from_u64(0))
+        .with_partition(
+            sim::Partition::new(
+                Instant(0),
+                Instant(100),
+                vec![a("p0"), a("a0")],
+                vec![a("a1"), a("a2")],
+            )
+            .one_way(),
+        )
+        .with_partition(
+            sim::Partition::new(Instant(100), Instant(150), vec![a("a1")], vec![a("p0")]).holding(),
+        )
+        .build()
+        .unwrap();
+    s.run().unwrap();
+    s.ensure_correctness().unwrap();
 
-    match simulator.ensure_correctness() {
-        Ok(()) => (),
-        Err(e) => {
-            for l in simulator.log.iter() {
-                println!("{}", l);
-            }
-            return TestResult::error(e);
+    assert!(s.responses()[0].header.at > Instant(100));
+    assert!(s.stats().dropped > 0);
+    assert!(s.stats().held > 0);
+}
+
+#[test]
+fn crash_recovery() {
+    let a = Address::new;
+
+    // At any point in time a majority of acceptors is up, though each of them
+    // loses its volatile state at least once.
+    let mut s = sim::Builder::new(classic::Proposer::new, classic::Acceptor::new)
+        .with_proposers(2)
+        .with_acceptors(3)
+        .with_requests(vec![(1, 0), (2, 1), (40, 1), (80, 0), (130, 1)])
+        .with_msg_delay_rng(StdRng::seed_from_u64(0))
+        .with_crash(sim::Crash::recover(a("a0"), Instant(3), Instant(60)))
+        .with_crash(sim::Crash::recover(a("a1"), Instant(60), Instant(120)))
+        .with_crash(sim::Crash::stop(a("a2"), Instant(150)))
+        .with_monitor()
+        .build()
+        .unwrap();
+    s.run().unwrap();
+    s.ensure_correctness().unwrap();
+
+    // Neither a3 nor c0, sending injected requests, can crash.
+    for node in ["a3", "c0"] {
+        let s =
+            sim::Builder::<_, _, _, StdRng>::new(classic::Proposer::new, classic::Acceptor::new)
+                .with_proposers(2)
+                .with_acceptors(3)
+                .with_requests(vec![(1, 0)])
+                .with_crash(sim::Crash::stop(a(node), Instant(2)))
+                .build();
+        assert!(s.is_err());
+    }
+}
+
+fn storage_acceptor(reply_before_persisting: bool) -> impl Fn(Address) -> classic::Acceptor {
+    move |address| {
+        let storage = sim::SimulatedStorage::new(StdRng::seed_from_u64(0))
+            .with_write_latency(Instant(5))
+            .losing_unsynced_on_crash();
+        let acceptor = classic::Acceptor::with_storage(address, Box::new(storage));
+        if reply_before_persisting {
+            acceptor.reply_before_persisting()
+        } else {
+            acceptor
         }
+    }
+}
+
+#[test]
+fn storage_write_latency() {
+    let mut s = sim::Builder::new(classic::Proposer::new, storage_acceptor(false))
+        .with_proposers(2)
+        .with_acceptors(3)
+        .with_requests(vec![(1, 0), (2, 1), (40, 1)])
+        .with_msg_delay_rng(StdRng::seed_from_u64(0))
+        .build()
+        .unwrap();
+    s.run().unwrap();
+    s.ensure_correctness().unwrap();
+}
+
+#[test]
+fn reply_before_persisting_is_unsafe() {
+    let a = Address::new;
+
+    // p0 gets its value accepted by all acceptors before tick 6, yet none of
+    // them persisted it by the time they all crash. p1 then gets a different
+    // value chosen.
+    let run = |reply_before_persisting| {
+        let mut s = sim::Builder::<_, _, _, StdRng>::new(
+            classic::Proposer::new,
+            storage_acceptor(reply_before_persisting),
+        )
+        .with_proposers(2)
+        .with_acceptors(3)
+        .with_requests(vec![(1, 0), (15, 1)])
+        .with_crash(sim::Crash::recover(a("a0"), Instant(6), Instant(7)))
+        .with_crash(sim::Crash::recover(a("a1"), Instant(6), Instant(7)))
+        .with_crash(sim::Crash::recover(a("a2"), Instant(6), Instant(7)))
+        .build()
+        .unwrap();
+        s.run().unwrap();
+        s.ensure_correctness()
     };
 
-    let classic_duration = simulator.get_now();
+    assert!(run(true).is_err());
+    assert!(run(false).is_ok());
+}
 
-    let mut rng = StdRng::seed_from_u64(seed);
+#[test]
+fn amnesia_violates_safety() {
+    let a = Address::new;
 
-    let requests = request_instants
-        .iter()
-        .map(|i| (*i, rng.gen_range(0, proposers)))
-        .collect();
+    // p0 gets v0 chosen by all acceptors. a0 and a1 then forget about it
+    // while a2 stays down, thus p1 gets v1 chosen by a0 and a1.
+    let run = |amnesia| {
+        let crash = |node| {
+            let c = sim::Crash::recover(a(node), Instant(8), Instant(10));
+            if amnesia {
+                c.amnesia()
+            } else {
+                c
+            }
+        };
+        let mut s =
+            sim::Builder::<_, _, _, StdRng>::new(classic::Proposer::new, classic::Acceptor::new)
+                .with_proposers(2)
+                .with_acceptors(3)
+                .with_requests(vec![(1, 0), (15, 1)])
+                .with_crash(crash("a0"))
+                .with_crash(crash("a1"))
+                .with_crash(sim::Crash::stop(a("a2"), Instant(8)))
+                .build()
+                .unwrap();
+        s.run().unwrap();
+        s.ensure_correctness()
+    };
 
-    let mut simulator = NackPaxosBuilder::<StdRng>::new()
-        .with_proposers(proposers)
-        .with_accpetors(acceptors)
-        .with_requests(requests)
-        .with_msg_delay_rng(rng)
+    let err = run(true).unwrap_err();
+    assert!(err.contains("causal chain of \"v0\""));
+    assert!(err.contains("causal chain of \"v1\""));
+
+    run(false).unwrap();
+
+    // Proposers have no persisted state to lose.
+    let s = sim::Builder::<_, _, _, StdRng>::new(classic::Proposer::new, classic::Acceptor::new)
+        .with_proposers(2)
+        .with_acceptors(3)
+        .with_crash(sim::Crash::recover(a("p0"), Instant(8), Instant(10)).amnesia())
         .build();
+    assert!(s.is_err());
+}
 
-    simulator.run().unwrap();
+#[test]
+fn monitor_catches_amnesia() {
+    let a = Address::new;
 
-    match simulator.ensure_correctness() {
-        Ok(()) => (),
-        Err(e) => {
-            for l in simulator.log.iter() {
-                println!("{}", l);
+    let mut s =
+        sim::Builder::<_, _, _, StdRng>::new(classic::Proposer::new, classic::Acceptor::new)
+            .with_proposers(2)
+            .with_acceptors(3)
+            .with_requests(vec![(1, 0), (15, 1)])
+            .with_crash(sim::Crash::recover(a("a0"), Instant(8), Instant(10)).amnesia())
+            .with_crash(sim::Crash::recover(a("a1"), Instant(8), Instant(10)).amnesia())
+            .with_crash(sim::Crash::stop(a("a2"), Instant(8)))
+            .with_monitor()
+            .build()
+            .unwrap();
+    assert!(s.run().is_err());
+
+    let v = s.violation().unwrap();
+    assert_eq!(v.invariant, sim::Invariant::ProposeChosenValue);
+    assert!(v.nodes.contains(&a("p1")));
+    // Stopped right away, before p1 could respond.
+    assert_eq!(s.responses().len(), 1);
+}
+
+#[test]
+fn monitor_records_each_accept() {
+    use paxos_simulator::storage::AcceptorState;
+    use std::collections::BTreeMap;
+
+    let a = Address::new;
+    let accepted = |epoch, v| -> BTreeMap<Slot, AcceptorState> {
+        let state = AcceptorState {
+            promised_epoch: Some(epoch),
+            accepted: Some((epoch, Value::new(v))),
+        };
+        vec![(Slot(0), state)].into_iter().collect()
+    };
+
+    let mut monitor = sim::Monitor::default();
+    let initial = vec![
+        (a("a0"), BTreeMap::new()),
+        (a("a1"), BTreeMap::new()),
+        (a("a2"), BTreeMap::new()),
+    ];
+    monitor
+        .check::<classic::Body>(Instant(0), initial.into_iter().collect(), &[])
+        .unwrap();
+
+    // a1 helps choosing v0, then moves on to v1 within the same tick, never
+    // holding v0 at the end of the tick.
+    let (e1, e2) = (Epoch::new(0, 1), Epoch::new(0, 2));
+    monitor
+        .record::<classic::Body>(Instant(1), a("a0"), accepted(e1, "v0"))
+        .unwrap();
+    monitor
+        .record::<classic::Body>(Instant(1), a("a1"), accepted(e1, "v0"))
+        .unwrap();
+    monitor
+        .record::<classic::Body>(Instant(1), a("a1"), accepted(e2, "v1"))
+        .unwrap();
+    let violation = monitor
+        .record::<classic::Body>(Instant(1), a("a2"), accepted(e2, "v1"))
+        .unwrap_err();
+    assert_eq!(violation.invariant, sim::Invariant::SingleChosenValue);
+    assert_eq!(monitor.chosen()[&Slot(0)], (e1, Value::new("v0")));
+}
+
+#[test]
+fn multi_paxos() {
+    let a = Address::new;
+
+    let mut s = sim::Builder::new(multi::Proposer::new, multi::Acceptor::new)
+        .with_proposers(2)
+        .with_acceptors(3)
+        .with_requests(vec![(1, 0), (2, 1), (3, 0), (40, 1), (41, 0), (80, 1)])
+        .with_msg_delay_rng(StdRng::seed_from_u64(0))
+        .with_crash(sim::Crash::recover(a("a0"), Instant(20), Instant(60)))
+        .with_monitor()
+        .build()
+        .unwrap();
+    s.run().unwrap();
+    s.ensure_correctness().unwrap();
+
+    let mut slots: Vec<_> = s.responses().iter().map(|r| r.body.slot()).collect();
+    slots.sort();
+    slots.dedup();
+    assert_eq!(slots.len(), 6);
+}
+
+#[test]
+fn multi_paxos_equal_values() {
+    use paxos_simulator::{Header, Msg, Node};
+
+    // Two requests of the same value, the first proposed before p0 times
+    // out, the second arriving while p0 prepares again. Both need a slot of
+    // their own.
+    let (c0, p0, a0) = (Address::new("c0"), Address::new("p0"), Address::new("a0"));
+    let mut proposer = multi::Proposer::new(p0.clone(), Epoch::new(0, 0), vec![a0.clone()]);
+    let mut acceptor = multi::Acceptor::new(a0);
+    let request = |id| Msg {
+        header: Header {
+            from: c0.clone(),
+            to: p0.clone(),
+            at: Instant(1),
+        },
+        body: multi::Body::Request(RequestId(id), Value::new("x")),
+    };
+    let mut exchange = |msgs: Vec<Msg<multi::Body>>, now| {
+        msgs.into_iter().for_each(|m| acceptor.receive(m));
+        acceptor.process(Instant(now))
+    };
+
+    proposer.receive(request(0));
+    let prepares = proposer.process(Instant(1));
+    exchange(prepares, 2)
+        .into_iter()
+        .for_each(|m| proposer.receive(m));
+    let proposals = proposer.process(Instant(3));
+    // The accept gets lost.
+    assert_eq!(exchange(proposals, 4).len(), 1);
+
+    let prepares = proposer.process(Instant(13));
+    proposer.receive(request(1));
+    assert!(proposer.process(Instant(14)).is_empty());
+    exchange(prepares, 15)
+        .into_iter()
+        .for_each(|m| proposer.receive(m));
+    let proposals = proposer.process(Instant(16));
+    exchange(proposals, 17)
+        .into_iter()
+        .for_each(|m| proposer.receive(m));
+
+    let responses: Vec<multi::Body> = proposer
+        .process(Instant(18))
+        .into_iter()
+        .map(|m| m.body)
+        .collect();
+    assert_eq!(
+        responses,
+        vec![
+            multi::Body::Response(RequestId(0), Slot(0), Value::new("x")),
+            multi::Body::Response(RequestId(1), Slot(1), Value::new("x")),
+        ]
+    );
+}
+
+#[test]
+fn multi_paxos_storage() {
+    let a = Address::new;
+    let storage_acceptor = |reply_before_persisting| {
+        move |address| {
+            let storage = sim::SimulatedStorage::new(StdRng::seed_from_u64(0))
+                .with_write_latency(Instant(5))
+                .losing_unsynced_on_crash();
+            let acceptor = multi::Acceptor::with_storage(address, Box::new(storage));
+            if reply_before_persisting {
+                acceptor.reply_before_persisting()
+            } else {
+                acceptor
             }
-            return TestResult::error(e);
         }
-    }
+    };
 
-    let nack_duration = simulator.get_now();
+    // Writes are slow and occasionally fail, a0 losing the ones in flight.
+    let slow_acceptor = |address| {
+        let storage = sim::SimulatedStorage::new(StdRng::seed_from_u64(1))
+            .with_write_latency(Instant(3))
+            .with_failure_probability(0.2)
+            .losing_unsynced_on_crash();
+        multi::Acceptor::with_storage(address, Box::new(storage))
+    };
+    let mut s = sim::Builder::new(multi::Proposer::new, slow_acceptor)
+        .with_proposers(2)
+        .with_acceptors(3)
+        .with_requests(vec![(1, 0), (2, 1), (40, 1)])
+        .with_msg_delay_rng(StdRng::seed_from_u64(0))
+        .with_crash(sim::Crash::recover(a("a0"), Instant(10), Instant(20)))
+        .build()
+        .unwrap();
+    s.run().unwrap();
+    s.ensure_correctness().unwrap();
 
-    println!("classic: {:?}, nack: {:?}", classic_duration, nack_duration);
+    // Like with a single instance, p0 gets its value accepted for slot 0
+    // before any acceptor persisted it, thus p1 can get a different one
+    // chosen for the same slot after all of them crashed.
+    let run = |reply_before_persisting| {
+        let mut s = sim::Builder::<_, _, _, StdRng>::new(
+            multi::Proposer::new,
+            storage_acceptor(reply_before_persisting),
+        )
+        .with_proposers(2)
+        .with_acceptors(3)
+        .with_requests(vec![(1, 0), (15, 1)])
+        .with_crash(sim::Crash::recover(a("a0"), Instant(6), Instant(7)))
+        .with_crash(sim::Crash::recover(a("a1"), Instant(6), Instant(7)))
+        .with_crash(sim::Crash::recover(a("a2"), Instant(6), Instant(7)))
+        .with_monitor()
+        .build()
+        .unwrap();
+        s.run().and_then(|_| s.ensure_correctness())
+    };
 
-    TestResult::passed()
+    assert!(run(true).is_err());
+    assert!(run(false).is_ok());
 }
 
-trait Builder<A: Acceptor<B>, P: Proposer<B>, B: Body, Rng: rand::Rng> {
-    fn with_proposers(self, size: u32) -> Self;
-    fn with_accpetors(self, size: u32) -> Self;
-    fn with_requests(self, r: Vec<(u64, u32)>) -> Self;
-    fn with_msg_delay_rng(self, rng: Rng) -> Self;
+#[test]
+fn multi_paxos_stable_leader() {
+    let mut s = sim::Builder::new(multi::Proposer::new, multi::Acceptor::new)
+        .with_proposers(1)
+        .with_acceptors(3)
+        .with_requests(vec![(1, 0), (2, 0), (20, 0), (21, 0), (50, 0)])
+        .with_msg_delay_rng(StdRng::seed_from_u64(0))
+        .build()
+        .unwrap();
+    s.run().unwrap();
+    s.ensure_correctness().unwrap();
+
+    // Only the first request requires phase 1.
+    let prepares = s
+        .deliveries()
+        .iter()
+        .filter(|d| matches!(d.msg.body, multi::Body::Prepare(_)))
+        .count();
+    assert_eq!(prepares, 3);
 
-    fn build(self) -> simulator::Simulator<A, P, B, Rng>;
+    let mut slots: Vec<_> = s.responses().iter().map(|r| r.body.slot()).collect();
+    slots.sort();
+    assert_eq!(slots, (0..5).map(Slot).collect::<Vec<_>>());
 }
 
-#[derive(Default)]
-struct ClassicPaxosBuilder<Rng: rand::Rng> {
-    a: HashMap<Address, classic::Acceptor>,
-    p: HashMap<Address, classic::Proposer>,
-    r: Vec<Msg<classic::Body>>,
-    msg_delay_rng: Option<Rng>,
+#[test]
+fn leader_election() {
+    let a = Address::new;
+
+    // Requests are forwarded to p0 until it crashes, p1 taking over
+    // afterwards.
+    let mut s = sim::Builder::new(
+        |a, e, acceptors| multi::Proposer::new(a, e, acceptors).with_election(),
+        multi::Acceptor::new,
+    )
+    .with_proposers(3)
+    .with_acceptors(3)
+    .with_requests(vec![(1, 0), (2, 1), (3, 2), (30, 2), (80, 1), (81, 2)])
+    .with_msg_delay_rng(StdRng::seed_from_u64(0))
+    .with_crash(sim::Crash::stop(a("p0"), Instant(50)))
+    .with_monitor()
+    .build()
+    .unwrap();
+    s.run().unwrap();
+    s.ensure_correctness().unwrap();
+
+    let prepared_by = |p: &str| {
+        s.deliveries()
+            .iter()
+            .filter(|d| d.msg.header.from == p && matches!(d.msg.body, multi::Body::Prepare(_)))
+            .count()
+    };
+    assert_eq!(prepared_by("p0"), 3);
+    assert_eq!(prepared_by("p1"), 3);
+    assert_eq!(prepared_by("p2"), 0);
+
+    let now = s.get_now();
+    assert_eq!(*s.proposers()[&a("p2")].leader(now), a("p1"));
 }
 
-impl<Rng: rand::Rng> ClassicPaxosBuilder<Rng> {
-    fn new() -> Self {
-        ClassicPaxosBuilder {
-            a: Default::default(),
-            p: Default::default(),
-            r: Default::default(),
-            msg_delay_rng: None,
+#[test]
+fn resubmitted_after_leader_change() {
+    let a = Address::new;
+
+    // p0 gets r0 chosen in slot 0 but crashes before answering. p1 takes
+    // over, recovering r0 while appending r1. r0 resubmitted while recovered
+    // and still in flight, or once chosen, is answered from the log instead
+    // of being appended again.
+    for resubmitted_at in [63, 100] {
+        let mut s = sim::Builder::<_, _, _, StdRng>::new(
+            |a, e, acceptors| multi::Proposer::new(a, e, acceptors).with_election(),
+            multi::Acceptor::new,
+        )
+        .with_proposers(3)
+        .with_acceptors(3)
+        .with_requests(vec![(1, 0), (60, 1)])
+        .with_resubmission(0, resubmitted_at, 1)
+        .with_crash(sim::Crash::stop(a("p0"), Instant(4)))
+        .with_monitor()
+        .build()
+        .unwrap();
+        s.run().unwrap();
+        s.ensure_correctness().unwrap();
+
+        let chosen = s.proposers()[&a("p1")].chosen();
+        for id in [RequestId(0), RequestId(1)] {
+            let slots: Vec<&Slot> = chosen
+                .iter()
+                .filter(|(_, c)| matches!(c, multi::Command::Request(i, _) if *i == id))
+                .map(|(s, _)| s)
+                .collect();
+            assert_eq!(slots.len(), 1, "{:?} chosen in {:?}", id, slots);
         }
+        let answers: Vec<_> = s.responses().iter().map(|r| r.body.clone()).collect();
+        assert_eq!(answers.len(), 2);
+        assert!(answers.contains(&multi::Body::Response(
+            RequestId(0),
+            Slot(0),
+            Value::new("v0")
+        )));
+        assert!(answers.contains(&multi::Body::Response(
+            RequestId(1),
+            Slot(1),
+            Value::new("v1")
+        )));
     }
 }
 
-impl<Rng: rand::Rng> Builder<classic::Acceptor, classic::Proposer, classic::Body, Rng>
-    for ClassicPaxosBuilder<Rng>
-{
-    fn with_proposers(mut self, size: u32) -> Self {
-        for i in 0..size {
-            let name = format!("p{}", i);
+#[test]
+fn responses_are_routed_to_clients() {
+    let a = Address::new;
 
-            self.p.insert(
-                Address::new(&name),
-                classic::Proposer::new(Address::new(&name), Epoch::new(0, i), vec![]),
-            );
-        }
+    // Requests by c1 reach the leader forwarded by followers, responses still
+    // going to c1.
+    let mut s = sim::Builder::new(
+        |a, e, acceptors| multi::Proposer::new(a, e, acceptors).with_election(),
+        multi::Acceptor::new,
+    )
+    .with_proposers(3)
+    .with_acceptors(3)
+    .with_requests(vec![(1, 0), (20, 0)])
+    .with_requests_from(1, vec![(2, 1), (21, 2)])
+    .with_requests_from(2, vec![(3, 2)])
+    .with_msg_delay_rng(StdRng::seed_from_u64(0))
+    .build()
+    .unwrap();
+    s.run().unwrap();
+    s.ensure_correctness().unwrap();
 
-        self
+    assert_eq!(s.clients().len(), 3);
+    for r in s.requests() {
+        let value = r.body.is_request();
+        let response = s
+            .responses()
+            .iter()
+            .find(|m| m.body.is_response() == value)
+            .unwrap();
+        assert_eq!(response.header.to, r.header.from);
     }
+    assert!(s
+        .deliveries()
+        .iter()
+        .any(|d| d.msg.body == multi::Body::Forward(a("c1"), RequestId(2), Value::new("v2"))));
+}
+
+#[test]
+fn clients_retry_on_timeout() {
+    let a = Address::new;
 
-    fn with_accpetors(mut self, size: u32) -> Self {
-        for i in 0..size {
-            let name = format!("a{}", i);
+    // p0 is down, thus requests sent to it are retried against p1.
+    let mut s = sim::Builder::new(classic::Proposer::new, classic::Acceptor::new)
+        .with_proposers(2)
+        .with_acceptors(3)
+        .with_requests(vec![(1, 0)])
+        .with_requests_from(1, vec![(5, 1)])
+        .with_msg_delay_rng(StdRng::seed_from_u64(0))
+        .with_crash(sim::Crash::stop(a("p0"), Instant(1)))
+        .with_clients(Instant(30), 3)
+        .build()
+        .unwrap();
+    s.run().unwrap();
+    s.ensure_correctness().unwrap();
 
-            self.a.insert(
-                Address::new(&name),
-                classic::Acceptor::new(Address::new(&name)),
-            );
-        }
+    let c0 = s.simulated_clients()[&a("c0")].history();
+    assert_eq!(c0.len(), 1);
+    assert_eq!(
+        c0[0].attempts,
+        vec![(Instant(1), a("p0")), (Instant(31), a("p1"))]
+    );
+    assert!(c0[0].latency().unwrap() > Instant(30));
 
-        self
-    }
+    let c1 = s.simulated_clients()[&a("c1")].history();
+    assert_eq!(c1[0].attempts.len(), 1);
+    assert!(c1[0].latency().unwrap() < Instant(30));
 
-    fn with_requests(mut self, r: Vec<(u64, u32)>) -> Self {
-        for (i, (instant, proposer)) in r.iter().enumerate() {
-            let name = format!("p{}", proposer);
-            let value = format!("v{}", i);
-
-            self.r.push(Msg {
-                header: Header {
-                    from: Address::new("u1"),
-                    to: Address::new(&name),
-                    at: Instant(*instant),
-                },
-                body: classic::Body::Request(Value::new(&value)),
-            });
-        }
+    // Without any proposer left, clients give up after their last attempt.
+    let mut s =
+        sim::Builder::<_, _, _, StdRng>::new(classic::Proposer::new, classic::Acceptor::new)
+            .with_proposers(1)
+            .with_acceptors(3)
+            .with_requests(vec![(1, 0)])
+            .with_crash(sim::Crash::stop(a("p0"), Instant(1)))
+            .with_clients(Instant(10), 2)
+            .build()
+            .unwrap();
+    s.run().unwrap();
+    assert!(s.ensure_correctness().is_err());
 
-        self
-    }
+    let c0 = &s.simulated_clients()[&a("c0")].history()[0];
+    assert_eq!(c0.attempts.len(), 2);
+    assert!(c0.abandoned);
+    assert_eq!(c0.latency(), None);
 
-    fn with_msg_delay_rng(mut self, rng: Rng) -> Self {
-        self.msg_delay_rng = Some(rng);
-        self
-    }
+    // Clients need a proposer to send their requests to in the first place.
+    let s = sim::Builder::<_, _, _, StdRng>::new(classic::Proposer::new, classic::Acceptor::new)
+        .with_acceptors(3)
+        .with_requests(vec![(1, 0)])
+        .with_clients(Instant(10), 2)
+        .build();
+    assert!(s.is_err());
+}
 
-    fn build(
-        self,
-    ) -> simulator::Simulator<classic::Acceptor, classic::Proposer, classic::Body, Rng> {
-        let a_addresses: Vec<Address> = self.a.iter().map(|(_, a)| a.address()).collect();
-        let p = self
-            .p
-            .into_iter()
-            .map(|(address, mut proposer)| {
-                proposer.acceptors = a_addresses.clone();
-                (address, proposer)
-            })
-            .collect();
+#[test]
+fn resubmitted_requests() {
+    let a = Address::new;
 
-        simulator::Simulator::new(p, self.a, self.r, self.msg_delay_rng)
-    }
+    // r0 is resubmitted while in flight, r1 once answered. Neither is
+    // appended to the log twice.
+    let mut s = sim::Builder::<_, _, _, StdRng>::new(multi::Proposer::new, multi::Acceptor::new)
+        .with_proposers(1)
+        .with_acceptors(3)
+        .with_requests(vec![(1, 0), (2, 0)])
+        .with_resubmission(0, 2, 0)
+        .with_resubmission(1, 60, 0)
+        .build()
+        .unwrap();
+    s.run().unwrap();
+    s.ensure_correctness().unwrap();
+
+    assert_eq!(s.proposers()[&a("p0")].chosen().len(), 2);
+    let answers: Vec<_> = s
+        .responses()
+        .iter()
+        .map(|r| r.body.is_response().unwrap().0)
+        .collect();
+    assert_eq!(answers, vec![RequestId(0), RequestId(1), RequestId(1)]);
+
+    // Resubmitted to another proposer, both answer with the same value.
+    let mut s = sim::Builder::new(classic::Proposer::new, classic::Acceptor::new)
+        .with_proposers(2)
+        .with_acceptors(3)
+        .with_requests(vec![(1, 0)])
+        .with_resubmission(0, 1, 1)
+        .with_msg_delay_rng(StdRng::seed_from_u64(0))
+        .build()
+        .unwrap();
+    s.run().unwrap();
+    s.ensure_correctness().unwrap();
+    assert_eq!(s.responses().len(), 2);
+
+    // There is no request to resubmit.
+    let s = sim::Builder::<_, _, _, StdRng>::new(classic::Proposer::new, classic::Acceptor::new)
+        .with_proposers(1)
+        .with_acceptors(3)
+        .with_requests(vec![(1, 0)])
+        .with_resubmission(1, 2, 0)
+        .build();
+    assert!(s.is_err());
 }
 
-#[derive(Default)]
-struct NackPaxosBuilder<Rng: rand::Rng> {
-    a: HashMap<Address, nack::Acceptor>,
-    p: HashMap<Address, nack::Proposer>,
-    r: Vec<Msg<nack::Body>>,
-    msg_delay_rng: Option<Rng>,
+#[test]
+fn bounded_request_queue() {
+    let a = Address::new;
+
+    // The first request is handled right away, the second queued and the
+    // third rejected.
+    let mut s = sim::Builder::<_, _, _, StdRng>::new(
+        |a, e, acceptors| classic::Proposer::new(a, e, acceptors).with_queue_capacity(1),
+        classic::Acceptor::new,
+    )
+    .with_proposers(1)
+    .with_acceptors(3)
+    .with_requests(vec![(1, 0), (1, 0), (1, 0)])
+    .build()
+    .unwrap();
+    s.run().unwrap();
+    s.ensure_correctness().unwrap();
+
+    let answered: Vec<_> = s
+        .responses()
+        .iter()
+        .map(|r| r.body.is_response().unwrap().0)
+        .collect();
+    assert_eq!(answered, vec![RequestId(0), RequestId(1)]);
+    assert_eq!(s.rejections().len(), 1);
+    assert_eq!(s.rejections()[0].body, classic::Body::Busy(RequestId(2)));
+
+    // Clients retry rejected requests against the next proposer right away.
+    let mut s = sim::Builder::<_, _, _, StdRng>::new(
+        |a, e, acceptors| nack::Proposer::new(a, e, acceptors).with_queue_capacity(0),
+        nack::Acceptor::new,
+    )
+    .with_proposers(2)
+    .with_acceptors(3)
+    .with_requests(vec![(1, 0), (1, 0)])
+    .with_clients(Instant(30), 3)
+    .build()
+    .unwrap();
+    s.run().unwrap();
+    s.ensure_correctness().unwrap();
+
+    let history = s.simulated_clients()[&a("c0")].history();
+    assert_eq!(history[1].rejections, 1);
+    assert_eq!(history[1].attempts[1].1, a("p1"));
+    assert!(history[1].latency().unwrap() < Instant(30));
 }
 
-impl<Rng: rand::Rng> NackPaxosBuilder<Rng> {
-    fn new() -> Self {
-        NackPaxosBuilder {
-            a: Default::default(),
-            p: Default::default(),
-            r: Default::default(),
-            msg_delay_rng: None,
-        }
-    }
+#[test]
+fn stray_messages_to_proposers() {
+    use paxos_simulator::{Header, Msg, Node};
+
+    // Duplicated or misrouted messages meant for end-users and learners are
+    // ignored by proposers instead of crashing them.
+    let (c0, p0) = (Address::new("c0"), Address::new("p0"));
+    let acceptors = addresses(&["a0", "a1", "a2"]);
+    let msg = |body| Msg {
+        header: Header {
+            from: c0.clone(),
+            to: p0.clone(),
+            at: Instant(1),
+        },
+        body,
+    };
+
+    let mut proposer = classic::Proposer::new(p0.clone(), Epoch::new(0, 0), acceptors.clone());
+    proposer.receive(msg(classic::Body::Response(RequestId(0), Value::new("v0"))));
+    proposer.receive(msg(classic::Body::Busy(RequestId(0))));
+    proposer.receive(msg(classic::Body::Learned(
+        Epoch::new(0, 0),
+        Value::new("v0"),
+    )));
+    proposer.receive(msg(classic::Body::Decide(Value::new("v0"))));
+    assert!(proposer.process(Instant(1)).is_empty());
+
+    let msg = |body| Msg {
+        header: Header {
+            from: c0.clone(),
+            to: p0.clone(),
+            at: Instant(1),
+        },
+        body,
+    };
+    let mut proposer = fast::Proposer::new(p0.clone(), Epoch::new(0, 0), acceptors.clone());
+    proposer.receive(msg(fast::Body::Response(RequestId(0), Value::new("v0"))));
+    proposer.receive(msg(fast::Body::Busy(RequestId(0))));
+    assert!(proposer.process(Instant(1)).is_empty());
+
+    let msg = |body| Msg {
+        header: Header {
+            from: c0.clone(),
+            to: p0.clone(),
+            at: Instant(1),
+        },
+        body,
+    };
+    let mut proposer = multi::Proposer::new(p0.clone(), Epoch::new(0, 0), acceptors);
+    proposer.receive(msg(multi::Body::Response(
+        RequestId(0),
+        Slot(0),
+        Value::new("v0"),
+    )));
+    assert!(proposer.process(Instant(1)).is_empty());
 }
 
-impl<Rng: rand::Rng> Builder<nack::Acceptor, nack::Proposer, nack::Body, Rng>
-    for NackPaxosBuilder<Rng>
-{
-    fn with_proposers(mut self, size: u32) -> Self {
-        for i in 0..size {
-            let name = format!("p{}", i);
+fn lease_proposer(a: Address, e: Epoch, acceptors: Vec<Address>) -> multi::Proposer {
+    multi::Proposer::new(a, e, acceptors)
+        .with_election()
+        .with_lease(Instant(100))
+}
 
-            self.p.insert(
-                Address::new(&name),
-                nack::Proposer::new(Address::new(&name), Epoch::new(0, i), vec![]),
-            );
-        }
+fn lease_acceptor(a: Address) -> multi::Acceptor {
+    multi::Acceptor::new(a).with_lease(Instant(100))
+}
 
-        self
-    }
+#[test]
+fn leader_lease() {
+    let a = Address::new;
 
-    fn with_accpetors(mut self, size: u32) -> Self {
-        for i in 0..size {
-            let name = format!("a{}", i);
+    let mut s = sim::Builder::new(lease_proposer, lease_acceptor)
+        .with_proposers(2)
+        .with_acceptors(3)
+        .with_requests(vec![(1, 0), (2, 1), (20, 1)])
+        .with_msg_delay_rng(StdRng::seed_from_u64(0))
+        .with_monitor()
+        .build()
+        .unwrap();
+    s.run().unwrap();
+    s.ensure_correctness().unwrap();
 
-            self.a.insert(
-                Address::new(&name),
-                nack::Acceptor::new(Address::new(&name)),
-            );
-        }
+    // The leader keeps renewing its lease, serving reads locally.
+    let now = s.get_now();
+    let mut log = s.proposers()[&a("p0")].read(now).unwrap();
+    log.sort();
+    assert_eq!(
+        log,
+        vec![Value::new("v0"), Value::new("v1"), Value::new("v2")]
+    );
+    assert_eq!(s.proposers()[&a("p1")].read(now), None);
+}
 
-        self
-    }
+#[test]
+fn leader_lease_blocks_takeover() {
+    let a = Address::new;
 
-    fn with_requests(mut self, r: Vec<(u64, u32)>) -> Self {
-        for (i, (instant, proposer)) in r.iter().enumerate() {
-            let name = format!("p{}", proposer);
-            let value = format!("v{}", i);
-
-            self.r.push(Msg {
-                header: Header {
-                    from: Address::new("u1"),
-                    to: Address::new(&name),
-                    at: Instant(*instant),
-                },
-                body: nack::Body::Request(Value::new(&value)),
-            });
-        }
+    // p1 takes over once p0 crashed, though the acceptors only promise once
+    // the lease of p0 expired.
+    let mut s = sim::Builder::<_, _, _, StdRng>::new(lease_proposer, lease_acceptor)
+        .with_proposers(2)
+        .with_acceptors(3)
+        .with_requests(vec![(1, 0), (50, 1)])
+        .with_crash(sim::Crash::stop(a("p0"), Instant(20)))
+        .with_monitor()
+        .build()
+        .unwrap();
+    s.run().unwrap();
+    s.ensure_correctness().unwrap();
 
-        self
-    }
+    assert!(s.responses()[1].header.at > Instant(100));
+}
 
-    fn with_msg_delay_rng(mut self, rng: Rng) -> Self {
-        self.msg_delay_rng = Some(rng);
-        self
-    }
+#[test]
+fn fast_paxos() {
+    let mut s = sim::Builder::<_, _, _, StdRng>::new(fast::Proposer::new, fast::Acceptor::new)
+        .with_proposers(1)
+        .with_acceptors(3)
+        .with_requests(vec![(1, 0)])
+        .with_monitor()
+        .build()
+        .unwrap();
+    s.run().unwrap();
+    s.ensure_correctness().unwrap();
 
-    fn build(self) -> simulator::Simulator<nack::Acceptor, nack::Proposer, nack::Body, Rng> {
-        let a_addresses: Vec<Address> = self.a.iter().map(|(_, a)| a.address()).collect();
-        let p = self
-            .p
-            .into_iter()
-            .map(|(address, mut proposer)| {
-                proposer.acceptors = a_addresses.clone();
-                (address, proposer)
-            })
-            .collect();
+    // Without contention, the fast round needs no phase 1.
+    let prepares = s
+        .deliveries()
+        .iter()
+        .filter(|d| matches!(d.msg.body, fast::Body::Prepare(_)))
+        .count();
+    assert_eq!(prepares, 0);
 
-        simulator::Simulator::new(p, self.a, self.r, self.msg_delay_rng)
-    }
+    // The client proposes to the acceptors itself, the proposer only
+    // collecting their accepts. Thus the response takes two message delays.
+    let proposals: Vec<Address> = s
+        .deliveries()
+        .iter()
+        .filter(|d| d.msg.body.is_propose().is_some())
+        .map(|d| d.msg.header.from.clone())
+        .collect();
+    assert_eq!(proposals, vec![Address::new("c0"); 3]);
+    assert_eq!(s.responses()[0].header.at, Instant(3));
+}
+
+#[test]
+fn fast_paxos_collision() {
+    let mut s = sim::Builder::new(fast::Proposer::new, fast::Acceptor::new)
+        .with_proposers(2)
+        .with_acceptors(3)
+        .with_requests(vec![(1, 0), (1, 1)])
+        .with_msg_delay_rng(StdRng::seed_from_u64(0))
+        .with_network_model(network::Uniform::new(Instant(1), Instant(4)))
+        .with_clients(Instant(50), 1)
+        .with_monitor()
+        .build()
+        .unwrap();
+    s.run().unwrap();
+    s.ensure_correctness().unwrap();
+
+    // The concurrent requests, each proposed by the client, collided.
+    // Recovering through a classic round.
+    let prepares = s
+        .deliveries()
+        .iter()
+        .filter(|d| matches!(d.msg.body, fast::Body::Prepare(_)))
+        .count();
+    assert!(prepares > 0);
+}
+
+#[test]
+fn fast_quorums() {
+    let a = Address::new;
+    let chosen = |epoch, acceptors: &[Address], accepted: &[Address]| {
+        fast::Body::is_chosen(
+            epoch,
+            acceptors,
+            &accepted.iter().cloned().collect(),
+            &Quorums::Majority,
+        )
+    };
+    let classic = Epoch::new(1, 0);
+
+    // Three out of four acceptors for the fast round, a majority otherwise.
+    let acceptors = [a("a0"), a("a1"), a("a2"), a("a3")];
+    assert!(!chosen(fast::FAST_EPOCH, &acceptors, &acceptors[..2]));
+    assert!(chosen(fast::FAST_EPOCH, &acceptors, &acceptors[..3]));
+    assert!(!chosen(classic, &acceptors, &acceptors[..2]));
+    assert!(chosen(classic, &acceptors, &acceptors[..3]));
+
+    // Nothing is chosen without any acceptors.
+    assert!(!chosen(fast::FAST_EPOCH, &[], &[]));
+    assert!(!chosen(classic, &[], &[]));
+}
+
+#[test]
+fn flexible_quorums() {
+    let quorums = Quorums::Flexible {
+        phase1: 4,
+        phase2: 2,
+    };
+
+    let mut s = sim::Builder::new(classic::Proposer::new, classic::Acceptor::new)
+        .with_proposers(2)
+        .with_acceptors(5)
+        .with_requests(vec![(1, 0), (2, 1), (30, 0)])
+        .with_msg_delay_rng(StdRng::seed_from_u64(0))
+        .with_quorums(quorums)
+        .with_monitor()
+        .build()
+        .unwrap();
+    s.run().unwrap();
+    s.ensure_correctness().unwrap();
+
+    let mut s = sim::Builder::new(nack::Proposer::new, nack::Acceptor::new)
+        .with_proposers(2)
+        .with_acceptors(5)
+        .with_requests(vec![(1, 0), (2, 1), (30, 0)])
+        .with_msg_delay_rng(StdRng::seed_from_u64(0))
+        .with_quorums(quorums)
+        .with_monitor()
+        .build()
+        .unwrap();
+    s.run().unwrap();
+    s.ensure_correctness().unwrap();
+
+    // Two acceptors are enough to keep appending to the log once leading.
+    let mut s = sim::Builder::new(multi::Proposer::new, multi::Acceptor::new)
+        .with_proposers(1)
+        .with_acceptors(5)
+        .with_requests(vec![(1, 0), (30, 0), (31, 0), (60, 0)])
+        .with_msg_delay_rng(StdRng::seed_from_u64(0))
+        .with_crash(sim::Crash::stop(Address::new("a0"), Instant(20)))
+        .with_crash(sim::Crash::stop(Address::new("a1"), Instant(20)))
+        .with_crash(sim::Crash::stop(Address::new("a2"), Instant(20)))
+        .with_quorums(quorums)
+        .with_monitor()
+        .build()
+        .unwrap();
+    s.run().unwrap();
+    s.ensure_correctness().unwrap();
+}
+
+#[test]
+fn non_intersecting_quorums_are_rejected() {
+    for quorums in [
+        Quorums::Flexible {
+            phase1: 2,
+            phase2: 3,
+        },
+        Quorums::Flexible {
+            phase1: 6,
+            phase2: 3,
+        },
+        Quorums::Flexible {
+            phase1: 5,
+            phase2: 0,
+        },
+    ] {
+        let mut s = sim::Builder::new(classic::Proposer::new, classic::Acceptor::new)
+            .with_proposers(1)
+            .with_acceptors(5)
+            .with_requests(vec![(1, 0)])
+            .with_msg_delay_rng(StdRng::seed_from_u64(0))
+            .with_quorums(quorums)
+            .build()
+            .unwrap();
+        assert!(s.run().is_err());
+    }
+}
+
+fn addresses(names: &[&str]) -> Vec<Address> {
+    names.iter().map(|n| Address::new(n)).collect()
+}
+
+#[test]
+fn verify_quorum_systems() {
+    let grid = Grid {
+        rows: vec![
+            addresses(&["a0", "a1", "a2"]),
+            addresses(&["a3", "a4", "a5"]),
+        ],
+    };
+    let acceptors = addresses(&["a0", "a1", "a2", "a3", "a4", "a5"]);
+    quorum::verify(&grid, &acceptors).unwrap();
+    // A single row makes for a phase-2 quorum.
+    let row = addresses(&["a3", "a4", "a5"]).into_iter().collect();
+    assert!(grid.is_phase2_quorum(&acceptors, &row));
+    assert!(!grid.is_phase1_quorum(&acceptors, &row));
+
+    let hierarchical = Hierarchical {
+        groups: vec![
+            addresses(&["a0", "a1", "a2"]),
+            addresses(&["a3", "a4", "a5"]),
+            addresses(&["a6", "a7", "a8"]),
+        ],
+    };
+    let acceptors = addresses(&["a0", "a1", "a2", "a3", "a4", "a5", "a6", "a7", "a8"]);
+    quorum::verify(&hierarchical, &acceptors).unwrap();
+    // Four out of nine acceptors suffice, as long as they are spread well.
+    let spread = addresses(&["a0", "a1", "a3", "a4"]).into_iter().collect();
+    assert!(hierarchical.is_phase1_quorum(&acceptors, &spread));
+
+    let weighted = Weighted {
+        weights: vec![
+            (Address::new("a0"), 3),
+            (Address::new("a1"), 1),
+            (Address::new("a2"), 1),
+        ]
+        .into_iter()
+        .collect(),
+    };
+    let acceptors = addresses(&["a0", "a1", "a2"]);
+    quorum::verify(&weighted, &acceptors).unwrap();
+    let heavy = addresses(&["a0"]).into_iter().collect();
+    assert!(weighted.is_phase2_quorum(&acceptors, &heavy));
+
+    let acceptors = addresses(&["a0", "a1", "a2", "a3"]);
+    quorum::verify(&Quorums::Majority, &acceptors).unwrap();
+    quorum::verify(
+        &Quorums::Flexible {
+            phase1: 3,
+            phase2: 2,
+        },
+        &acceptors,
+    )
+    .unwrap();
+    quorum::verify(
+        &Quorums::Flexible {
+            phase1: 2,
+            phase2: 2,
+        },
+        &acceptors,
+    )
+    .unwrap_err();
+
+    // Sizes are checked without enumerating subsets, thus regardless of the
+    // number of acceptors.
+    let acceptors: Vec<Address> = (0..40).map(|i| Address::new(&format!("a{}", i))).collect();
+    quorum::verify(&Quorums::Majority, &acceptors).unwrap();
+    quorum::verify(
+        &Quorums::Flexible {
+            phase1: 20,
+            phase2: 20,
+        },
+        &acceptors,
+    )
+    .unwrap_err();
+    let rows = acceptors.chunks(5).map(|r| r.to_vec()).collect();
+    assert!(!quorum::is_verifiable(&Grid { rows }, &acceptors));
+}
+
+#[test]
+fn quorum_systems() {
+    let grid = Grid {
+        rows: vec![addresses(&["a0", "a1"]), addresses(&["a2", "a3"])],
+    };
+    let mut s = sim::Builder::new(classic::Proposer::new, classic::Acceptor::new)
+        .with_proposers(2)
+        .with_acceptors(4)
+        .with_requests(vec![(1, 0), (2, 1), (30, 0)])
+        .with_msg_delay_rng(StdRng::seed_from_u64(0))
+        .with_quorums(grid)
+        .with_monitor()
+        .build()
+        .unwrap();
+    s.run().unwrap();
+    s.ensure_correctness().unwrap();
+
+    let hierarchical = Hierarchical {
+        groups: vec![
+            addresses(&["a0", "a1", "a2"]),
+            addresses(&["a3", "a4", "a5"]),
+            addresses(&["a6", "a7", "a8"]),
+        ],
+    };
+    let mut s = sim::Builder::new(multi::Proposer::new, multi::Acceptor::new)
+        .with_proposers(2)
+        .with_acceptors(9)
+        .with_requests(vec![(1, 0), (2, 1), (30, 0), (31, 1)])
+        .with_msg_delay_rng(StdRng::seed_from_u64(0))
+        // Losing a whole group and one acceptor of another one.
+        .with_crash(sim::Crash::stop(Address::new("a0"), Instant(0)))
+        .with_crash(sim::Crash::stop(Address::new("a1"), Instant(0)))
+        .with_crash(sim::Crash::stop(Address::new("a2"), Instant(0)))
+        .with_crash(sim::Crash::stop(Address::new("a3"), Instant(0)))
+        .with_quorums(hierarchical)
+        .with_monitor()
+        .build()
+        .unwrap();
+    s.run().unwrap();
+    s.ensure_correctness().unwrap();
+
+    // Too many acceptors to enumerate their subsets, the majorities are
+    // verified nonetheless.
+    let mut s = sim::Builder::new(classic::Proposer::new, classic::Acceptor::new)
+        .with_proposers(2)
+        .with_acceptors(17)
+        .with_requests(vec![(1, 0), (2, 1)])
+        .with_msg_delay_rng(StdRng::seed_from_u64(0))
+        .build()
+        .unwrap();
+    s.run().unwrap();
+    s.ensure_correctness().unwrap();
+
+    // Others run unverified with a warning.
+    let rows = (0..4)
+        .map(|r| {
+            (0..5)
+                .map(|c| Address::new(&format!("a{}", r * 5 + c)))
+                .collect()
+        })
+        .collect();
+    let mut s = sim::Builder::new(classic::Proposer::new, classic::Acceptor::new)
+        .with_proposers(2)
+        .with_acceptors(20)
+        .with_requests(vec![(1, 0), (2, 1)])
+        .with_msg_delay_rng(StdRng::seed_from_u64(0))
+        .with_quorums(Grid { rows })
+        .build()
+        .unwrap();
+    s.run().unwrap();
+    s.ensure_correctness().unwrap();
+    assert!(s.log.iter().any(|l| l.starts_with("=== Warning")));
+
+    // The heavy acceptor along with any other one forms a quorum.
+    let weighted = Weighted {
+        weights: vec![
+            (Address::new("a0"), 3),
+            (Address::new("a1"), 1),
+            (Address::new("a2"), 1),
+            (Address::new("a3"), 1),
+        ]
+        .into_iter()
+        .collect(),
+    };
+    let mut s = sim::Builder::new(nack::Proposer::new, nack::Acceptor::new)
+        .with_proposers(2)
+        .with_acceptors(4)
+        .with_requests(vec![(1, 0), (2, 1), (30, 0)])
+        .with_msg_delay_rng(StdRng::seed_from_u64(0))
+        .with_crash(sim::Crash::stop(Address::new("a1"), Instant(0)))
+        .with_crash(sim::Crash::stop(Address::new("a2"), Instant(0)))
+        .with_quorums(weighted)
+        .with_monitor()
+        .build()
+        .unwrap();
+    s.run().unwrap();
+    s.ensure_correctness().unwrap();
+}
+
+#[test]
+fn learning_strategies() {
+    let mut costs = vec![];
+
+    for learning in [
+        Learning::Proposer,
+        Learning::Distinguished,
+        Learning::AllToAll,
+    ] {
+        let mut s = sim::Builder::new(classic::Proposer::new, classic::Acceptor::new)
+            .with_proposers(2)
+            .with_acceptors(3)
+            .with_learners(3, classic::Learner::new, learning)
+            .with_requests(vec![(1, 0), (2, 1)])
+            .with_msg_delay_rng(StdRng::seed_from_u64(0))
+            .with_monitor()
+            .build()
+            .unwrap();
+        s.run().unwrap();
+        s.ensure_correctness().unwrap();
+
+        let (_, decided) = s.responses()[0].body.is_response().unwrap();
+        for l in s.learners().values() {
+            assert_eq!(l.learned().get(&Slot(0)), Some(&decided));
+        }
+
+        let notifications = s
+            .deliveries()
+            .iter()
+            .filter(|d| {
+                matches!(
+                    d.msg.body,
+                    classic::Body::Learned(_, _) | classic::Body::Decide(_)
+                )
+            })
+            .count();
+        costs.push(notifications);
+    }
+
+    // Each learner hears from every acceptor with all-to-all learning.
+    assert!(costs[1] < costs[2], "{:?}", costs);
+}
+
+#[test]
+fn explore_classic() {
+    let exploration = sim::Explorer::new(classic::Proposer::new, classic::Acceptor::new)
+        .with_proposers(2)
+        .with_acceptors(2)
+        .with_requests(vec![0, 1])
+        .with_drops()
+        .run()
+        .unwrap();
+    assert!(!exploration.truncated);
+}
+
+#[test]
+fn explore_nack() {
+    // Proposers retry right away on a NACK, thus duelling proposers can go on
+    // forever.
+    let exploration = sim::Explorer::new(nack::Proposer::new, nack::Acceptor::new)
+        .with_proposers(2)
+        .with_acceptors(2)
+        .with_requests(vec![0, 1])
+        .with_max_depth(14)
+        .run()
+        .unwrap();
+    assert!(exploration.truncated);
+}
+
+#[test]
+fn explore_multi() {
+    let exploration = sim::Explorer::new(multi::Proposer::new, multi::Acceptor::new)
+        .with_proposers(2)
+        .with_acceptors(2)
+        .with_requests(vec![0, 1])
+        .run()
+        .unwrap();
+    assert!(!exploration.truncated);
+}
+
+#[test]
+fn explore_amnesia() {
+    let counterexample = sim::Explorer::new(classic::Proposer::new, classic::Acceptor::new)
+        .with_proposers(1)
+        .with_acceptors(3)
+        .with_requests(vec![0, 0])
+        .with_amnesia(1)
+        .run()
+        .unwrap_err();
+
+    // v0 is chosen by a0 and a1, a0 forgets, v1 is proposed after promises by
+    // a0 and a2.
+    assert_eq!(
+        counterexample.violation.invariant,
+        sim::Invariant::ProposeChosenValue
+    );
+    assert_eq!(counterexample.trace.len(), 15);
+    assert!(counterexample.trace[12].contains("amnesia"));
+}
+
+#[test]
+fn explore_flexible_quorums() {
+    let explorer = |quorums| {
+        sim::Explorer::new(classic::Proposer::new, classic::Acceptor::new)
+            .with_proposers(2)
+            .with_acceptors(2)
+            .with_requests(vec![0, 1])
+            .with_quorums(quorums)
+            .run()
+    };
+
+    let exploration = explorer(Quorums::Flexible {
+        phase1: 2,
+        phase2: 1,
+    })
+    .unwrap();
+    assert!(!exploration.truncated);
+
+    // A proposer can prepare and propose with an acceptor that never heard
+    // of the value the other proposer chose with the other acceptor.
+    explorer(Quorums::Flexible {
+        phase1: 1,
+        phase2: 1,
+    })
+    .unwrap_err();
+}
+
+#[test]
+fn file_storage_roundtrip() {
+    use paxos_simulator::storage::{AcceptorState, FileStorage, Storage};
+    use paxos_simulator::{Epoch, Value};
+
+    let path = std::env::temp_dir().join(format!("paxos-simulator-{}", std::process::id()));
+    let mut storage = FileStorage::new(path.clone());
+    assert_eq!(storage.read().unwrap(), AcceptorState::default());
+
+    let state = AcceptorState {
+        promised_epoch: Some(Epoch::new(3, 1)),
+        accepted: Some((Epoch::new(2, 0), Value::new("some value"))),
+    };
+    storage.write(&state, Instant(0)).unwrap();
+    assert_eq!(FileStorage::new(path.clone()).read().unwrap(), state);
+
+    // A value that can not be stored neither replaces the state nor leaves a
+    // temporary file behind.
+    let invalid = AcceptorState {
+        promised_epoch: Some(Epoch::new(4, 1)),
+        accepted: Some((Epoch::new(4, 1), Value::new("some\nvalue"))),
+    };
+    assert!(storage.write(&invalid, Instant(1)).is_err());
+    assert!(!path.with_extension("tmp").exists());
+    assert_eq!(storage.read().unwrap(), state);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn simulated_storage_pending_writes() {
+    use paxos_simulator::storage::{AcceptorState, Storage};
+
+    let promised = |round| AcceptorState {
+        promised_epoch: Some(Epoch::new(round, 0)),
+        accepted: None,
+    };
+    let mut storage = sim::SimulatedStorage::new(StdRng::seed_from_u64(0))
+        .with_write_latency(Instant(5))
+        .losing_unsynced_on_crash();
+
+    // The first write is durable at 5 despite the second one superseding it
+    // at 3, thus surviving the crash at 6 unlike the second one.
+    assert_eq!(storage.write(&promised(1), Instant(0)).unwrap(), Instant(5));
+    assert_eq!(storage.write(&promised(2), Instant(3)).unwrap(), Instant(8));
+    assert_eq!(storage.read().unwrap(), promised(2));
+    storage.crash(Instant(6));
+    assert_eq!(storage.read().unwrap(), promised(1));
+}
+
+fn retry_policies() -> Vec<nack::RetryPolicy> {
+    vec![
+        nack::RetryPolicy::Immediate,
+        nack::RetryPolicy::Fixed(Instant(5)),
+        nack::RetryPolicy::Exponential {
+            base: Instant(2),
+            max: Instant(40),
+        },
+    ]
+}
+
+#[test]
+fn nack_retry_policies() {
+    let mut nacks = vec![];
+    for policy in retry_policies() {
+        let mut s = sim::Builder::new(
+            move |a, e, acceptors| nack::Proposer::new(a, e, acceptors).with_retry_policy(policy),
+            nack::Acceptor::new,
+        )
+        .with_proposers(3)
+        .with_acceptors(3)
+        .with_requests(vec![(1, 0), (1, 1), (1, 2), (2, 0), (2, 1), (2, 2)])
+        .with_msg_delay_rng(StdRng::seed_from_u64(0))
+        .with_network_model(network::Uniform::new(Instant(1), Instant(6)))
+        .build()
+        .unwrap();
+        s.run().unwrap();
+        s.ensure_correctness().unwrap();
+
+        let decided_at = s.responses().iter().map(|r| r.header.at).max().unwrap();
+        println!("{:?}: decided at {:?}", policy, decided_at);
+        nacks.push(
+            s.deliveries()
+                .iter()
+                .filter(|d| matches!(d.msg.body, nack::Body::Nack(_, _)))
+                .count(),
+        );
+    }
+
+    // Proposers retrying right away keep nacking each other the most.
+    assert!(nacks[1] < nacks[0]);
+    assert!(nacks[2] < nacks[0]);
+}
+
+#[test]
+fn adaptive_timeouts() {
+    use paxos_simulator::timeout::{ProposerConfig, Timeout};
+
+    let mut timeout = Timeout::new(
+        ProposerConfig::adaptive(Instant(10), Instant(2), Instant(100)),
+        0,
+    );
+    assert_eq!(timeout.get(), Instant(10));
+    timeout.observe(Instant(20));
+    // Round-trip time plus four times half of it.
+    assert_eq!(timeout.get(), Instant(60));
+    for _ in 0..50 {
+        timeout.observe(Instant(20));
+    }
+    // Settles just above a steady round-trip time.
+    assert!(timeout.get() >= Instant(20) && timeout.get() < Instant(25));
+
+    // Round trips take longer than the default timeout, thus proposers
+    // with a fixed one keep starting over.
+    let simulate = |config| {
+        let mut s = sim::Builder::new(classic::Proposer::new, classic::Acceptor::new)
+            .with_proposers(2)
+            .with_acceptors(3)
+            .with_requests(vec![(1, 0), (1, 1), (30, 0), (30, 1)])
+            .with_msg_delay_rng(StdRng::seed_from_u64(0))
+            .with_network_model(network::Uniform::new(Instant(6), Instant(10)))
+            .with_proposer_config(config)
+            .build()
+            .unwrap();
+        s.run().unwrap();
+        s
+    };
+
+    let s = simulate(ProposerConfig::default());
+    assert!(s.responses().is_empty());
+
+    let s = simulate(ProposerConfig::adaptive(
+        Instant(10),
+        Instant(2),
+        Instant(200),
+    ));
+    s.ensure_correctness().unwrap();
+}
+
+#[test]
+fn randomized_timeouts() {
+    use paxos_simulator::timeout::ProposerConfig;
+
+    // Prepare rounds per decision with the fixed timeout first, followed by
+    // timeouts randomized over ever wider ranges.
+    let mut configs = vec![ProposerConfig::default()];
+    configs.extend(
+        vec![5, 10, 20, 40]
+            .into_iter()
+            .map(|spread| ProposerConfig::randomized(Instant(10), Instant(10 + spread), 0)),
+    );
+
+    let mut rounds = vec![];
+    for config in configs {
+        let mut s = sim::Builder::new(classic::Proposer::new, classic::Acceptor::new)
+            .with_proposers(3)
+            .with_acceptors(3)
+            .with_requests(vec![(1, 0), (1, 1), (1, 2), (30, 0), (30, 1), (30, 2)])
+            .with_msg_delay_rng(StdRng::seed_from_u64(0))
+            .with_network_model(network::Uniform::new(Instant(2), Instant(6)))
+            .with_proposer_config(config)
+            .build()
+            .unwrap();
+        s.run().unwrap();
+        s.ensure_correctness().unwrap();
+
+        let prepares = s
+            .deliveries()
+            .iter()
+            .filter(|d| matches!(d.msg.body, classic::Body::Prepare(_)))
+            .count();
+        let per_decision = prepares as f64 / 3.0 / s.responses().len() as f64;
+        let decided_at = s.responses().iter().map(|r| r.header.at).max().unwrap();
+        println!(
+            "{:?}: {:.2} prepare rounds per decision, decided at {:?}",
+            config.randomized, per_decision, decided_at
+        );
+        rounds.push(per_decision);
+    }
+
+    // Proposers timing out at different instants duel less.
+    assert!(rounds[1..].iter().all(|r| *r < rounds[0]));
+}
+
+#[test]
+fn timers() {
+    use paxos_simulator::{Header, Msg, Node, Timer};
+
+    let (p0, a0) = (Address::new("p0"), Address::new("a0"));
+    let mut proposer = classic::Proposer::new(p0.clone(), Epoch::new(0, 0), vec![a0.clone()]);
+    let mut acceptor = classic::Acceptor::new(a0.clone());
+    assert_eq!(proposer.timer(), Timer::Idle);
+    assert_eq!(acceptor.timer(), Timer::Idle);
+
+    proposer.receive(Msg {
+        header: Header {
+            from: Address::new("c0"),
+            to: p0,
+            at: Instant(1),
+        },
+        body: classic::Body::Request(RequestId(0), Value::new("v0")),
+    });
+    let prepares = proposer.process(Instant(1));
+    // Waiting for a promise until the default timeout expires.
+    assert_eq!(proposer.timer(), Timer::At(Instant(11)));
+
+    for m in prepares {
+        acceptor.receive(m);
+    }
+    assert_eq!(acceptor.process(Instant(2)).len(), 1);
+    assert_eq!(acceptor.timer(), Timer::Idle);
+
+    assert!(!Timer::Idle.is_due(Instant(100)));
+    assert!(Timer::At(Instant(11)).is_due(Instant(11)));
+    assert_eq!(
+        Timer::Idle.min(Timer::At(Instant(3))),
+        Timer::At(Instant(3))
+    );
+}
+
+#[test]
+fn accepting_implies_promising() {
+    use paxos_simulator::{Acceptor, Header, Msg, Node};
+
+    // The promise for 0-1 is held up in the network while p2 gets "v1"
+    // accepted at 0-2. p1 proposing "v0" at 0-1 afterwards must not replace
+    // it: "v1" might have been chosen with a0 being part of the quorum, a
+    // proposer of a higher epoch would then learn "v0" from a0 instead.
+    let (p1, p2, a0) = (Address::new("p1"), Address::new("p2"), Address::new("a0"));
+    let mut acceptor = classic::Acceptor::new(a0.clone());
+    let msg = |from: &Address, body| Msg {
+        header: Header {
+            from: from.clone(),
+            to: a0.clone(),
+            at: Instant(1),
+        },
+        body,
+    };
+
+    acceptor.receive(msg(&p1, classic::Body::Prepare(Epoch::new(0, 1))));
+    acceptor.receive(msg(
+        &p2,
+        classic::Body::Propose(Epoch::new(0, 2), Value::new("v1")),
+    ));
+    acceptor.receive(msg(
+        &p1,
+        classic::Body::Propose(Epoch::new(0, 1), Value::new("v0")),
+    ));
+    let replies = acceptor.process(Instant(1));
+
+    assert_eq!(replies.len(), 2);
+    assert!(replies
+        .iter()
+        .all(|m| m.header.to != p1 || matches!(m.body, classic::Body::Promise(..))));
+    assert_eq!(
+        acceptor.states()[&Slot(0)].accepted,
+        Some((Epoch::new(0, 2), Value::new("v1")))
+    );
+
+    // The NACK acceptor rejects the proposal of p1 instead.
+    let mut acceptor = nack::Acceptor::new(a0.clone());
+    let msg = |from: &Address, body| Msg {
+        header: Header {
+            from: from.clone(),
+            to: a0.clone(),
+            at: Instant(1),
+        },
+        body,
+    };
+    acceptor.receive(msg(&p1, nack::Body::Prepare(Epoch::new(0, 1))));
+    acceptor.receive(msg(
+        &p2,
+        nack::Body::Propose(Epoch::new(0, 2), Value::new("v1")),
+    ));
+    acceptor.receive(msg(
+        &p1,
+        nack::Body::Propose(Epoch::new(0, 1), Value::new("v0")),
+    ));
+    let replies = acceptor.process(Instant(1));
+
+    assert!(replies
+        .iter()
+        .any(|m| m.body == nack::Body::Nack(Epoch::new(0, 1), Epoch::new(0, 2))));
+    assert_eq!(
+        acceptor.states()[&Slot(0)].accepted,
+        Some((Epoch::new(0, 2), Value::new("v1")))
+    );
+
+    // The promise of a multi acceptor covers every slot.
+    let mut acceptor = multi::Acceptor::new(a0.clone());
+    let msg = |from: &Address, body| Msg {
+        header: Header {
+            from: from.clone(),
+            to: a0.clone(),
+            at: Instant(1),
+        },
+        body,
+    };
+    let command = |id, v| multi::Command::Request(RequestId(id), Value::new(v));
+    acceptor.receive(msg(&p1, multi::Body::Prepare(Epoch::new(0, 1))));
+    acceptor.receive(msg(
+        &p2,
+        multi::Body::Propose(Epoch::new(0, 2), Slot(0), command(1, "v1")),
+    ));
+    acceptor.receive(msg(
+        &p1,
+        multi::Body::Propose(Epoch::new(0, 1), Slot(0), command(0, "v0")),
+    ));
+    acceptor.receive(msg(
+        &p1,
+        multi::Body::Propose(Epoch::new(0, 1), Slot(1), command(0, "v0")),
+    ));
+    let replies = acceptor.process(Instant(1));
+
+    assert_eq!(replies.len(), 2);
+    assert_eq!(
+        acceptor.states()[&Slot(0)].accepted,
+        Some((Epoch::new(0, 2), Value::new("v1")))
+    );
+    assert!(!acceptor.states().contains_key(&Slot(1)));
+
+    // So does accepting in a classic round of Fast Paxos.
+    let mut acceptor = fast::Acceptor::new(a0.clone());
+    let msg = |from: &Address, body| Msg {
+        header: Header {
+            from: from.clone(),
+            to: a0.clone(),
+            at: Instant(1),
+        },
+        body,
+    };
+    acceptor.receive(msg(&p1, fast::Body::Prepare(Epoch::new(1, 1))));
+    acceptor.receive(msg(
+        &p2,
+        fast::Body::Propose(Epoch::new(1, 2), Value::new("v1")),
+    ));
+    acceptor.receive(msg(
+        &p1,
+        fast::Body::Propose(Epoch::new(1, 1), Value::new("v0")),
+    ));
+    let replies = acceptor.process(Instant(1));
+
+    assert_eq!(replies.len(), 2);
+    assert_eq!(
+        acceptor.states()[&Slot(0)].accepted,
+        Some((Epoch::new(1, 2), Value::new("v1")))
+    );
+}
+
+fn nack_policies() -> Vec<nack::NackPolicy> {
+    vec![
+        nack::NackPolicy::First,
+        nack::NackPolicy::Quorum,
+        nack::NackPolicy::QuorumOrTimeout(Instant(3)),
+    ]
+}
+
+/// Returns whether NACK proposers with the given policies can keep preempting
+/// each other until the simulation gives up, i.e. when retrying right away on
+/// the first nack. That is the default, keeping the behaviour the NACK
+/// proposer always had, which livelocks with a handful of concurrent
+/// proposers already.
+fn livelocks(retry_policy: nack::RetryPolicy, nack_policy: nack::NackPolicy) -> bool {
+    retry_policy == nack::RetryPolicy::Immediate && nack_policy == nack::NackPolicy::First
+}
+
+#[test]
+fn retry_after_nack_quorum() {
+    let mut prepares = vec![];
+    for policy in nack_policies() {
+        let mut s = sim::Builder::new(
+            move |a, e, acceptors| nack::Proposer::new(a, e, acceptors).with_nack_policy(policy),
+            nack::Acceptor::new,
+        )
+        .with_proposers(3)
+        .with_acceptors(5)
+        .with_requests(vec![(1, 0), (1, 1), (1, 2), (2, 0), (2, 1), (2, 2)])
+        .with_msg_delay_rng(StdRng::seed_from_u64(0))
+        .with_network_model(network::Uniform::new(Instant(1), Instant(6)))
+        .build()
+        .unwrap();
+        s.run().unwrap();
+        s.ensure_correctness().unwrap();
+
+        let decided_at = s.responses().iter().map(|r| r.header.at).max().unwrap();
+        println!("{:?}: decided at {:?}", policy, decided_at);
+        prepares.push(
+            s.deliveries()
+                .iter()
+                .filter(|d| matches!(d.msg.body, nack::Body::Prepare(_)))
+                .count(),
+        );
+    }
+
+    // Retrying on the first nack abandons epochs a quorum could still accept.
+    assert!(prepares[1] < prepares[0]);
+    assert!(prepares[2] < prepares[0]);
+}
+
+#[quickcheck]
+fn variable_requests(
+    proposers: u32,
+    acceptors: u32,
+    request_instants: Vec<u64>,
+    seed: u64,
+) -> TestResult {
+    if proposers == 0 || acceptors == 0 {
+        return TestResult::discard();
+    }
+
+    if proposers > 10 || acceptors > 10 || request_instants.len() > 100 {
+        return TestResult::discard();
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let requests = request_instants
+        .iter()
+        .map(|i| (*i, rng.gen_range(0, proposers)))
+        .collect();
+
+    let mut simulator = sim::Builder::new(classic::Proposer::new, classic::Acceptor::new)
+        .with_proposers(proposers)
+        .with_acceptors(acceptors)
+        .with_requests(requests)
+        .with_msg_delay_rng(rng)
+        .build()
+        .unwrap();
+
+    simulator.run().unwrap();
+
+    match simulator.ensure_correctness() {
+        Ok(()) => (),
+        Err(e) => {
+            for l in simulator.log.iter() {
+                println!("{}", l);
+            }
+            return TestResult::error(e);
+        }
+    };
+
+    let classic_duration = simulator.get_now();
+
+    // Time to decision of the NACK proposer with each retry and nack policy.
+    let mut nack_durations = vec![];
+    let policies = retry_policies()
+        .into_iter()
+        .flat_map(|r| nack_policies().into_iter().map(move |n| (r, n)));
+    for (retry_policy, nack_policy) in policies {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let requests = request_instants
+            .iter()
+            .map(|i| (*i, rng.gen_range(0, proposers)))
+            .collect();
+
+        let mut simulator = sim::Builder::new(
+            move |a, e, acceptors| {
+                nack::Proposer::new(a, e, acceptors)
+                    .with_retry_policy(retry_policy)
+                    .with_nack_policy(nack_policy)
+            },
+            nack::Acceptor::new,
+        )
+        .with_proposers(proposers)
+        .with_acceptors(acceptors)
+        .with_requests(requests)
+        .with_msg_delay_rng(rng)
+        .build()
+        .unwrap();
+
+        simulator.run().unwrap();
+
+        // Livelocking proposers may leave requests unanswered, though only
+        // if they were still duelling when the simulation gave up. Whatever
+        // they decided needs to be consistent nonetheless.
+        let result = match simulator.ensure_correctness() {
+            Err(_) if livelocks(retry_policy, nack_policy) && simulator.gave_up() => {
+                simulator.ensure_safety()
+            }
+            result => result,
+        };
+        match result {
+            Ok(()) => (),
+            Err(e) => {
+                for l in simulator.log.iter() {
+                    println!("{}", l);
+                }
+                return TestResult::error(e);
+            }
+        }
+
+        nack_durations.push((retry_policy, nack_policy, simulator.get_now()));
+    }
+
+    println!(
+        "classic: {:?}, nack: {:?}",
+        classic_duration, nack_durations
+    );
+
+    TestResult::passed()
+}
+
+#[quickcheck]
+fn faulty_network(
+    proposers: u32,
+    acceptors: u32,
+    request_instants: Vec<u64>,
+    seed: u64,
+) -> TestResult {
+    if proposers == 0 || acceptors == 0 {
+        return TestResult::discard();
+    }
+
+    if proposers > 10 || acceptors > 10 || request_instants.len() > 100 {
+        return TestResult::discard();
+    }
+
+    let faults = sim::Faults::new(sim::LinkFaults {
+        drop: 0.1,
+        duplicate: 0.1,
+        reorder: 0.1,
+    });
+
+    // A dropped request or response is never retransmitted, thus not every
+    // request gets answered. Whatever was decided needs to be consistent
+    // nonetheless.
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let requests = request_instants
+        .iter()
+        .map(|i| (*i, rng.gen_range(0, proposers)))
+        .collect();
+
+    let mut simulator = sim::Builder::new(classic::Proposer::new, classic::Acceptor::new)
+        .with_proposers(proposers)
+        .with_acceptors(acceptors)
+        .with_requests(requests)
+        .with_msg_delay_rng(rng)
+        .with_faults(faults.clone())
+        .with_monitor()
+        .build()
+        .unwrap();
+
+    simulator.run().unwrap();
+
+    match simulator.ensure_safety() {
+        Ok(()) => (),
+        Err(e) => {
+            for l in simulator.log.iter() {
+                println!("{}", l);
+            }
+            return TestResult::error(e);
+        }
+    };
+
+    let classic_stats = simulator.stats();
+
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let requests = request_instants
+        .iter()
+        .map(|i| (*i, rng.gen_range(0, proposers)))
+        .collect();
+
+    let mut simulator = sim::Builder::new(nack::Proposer::new, nack::Acceptor::new)
+        .with_proposers(proposers)
+        .with_acceptors(acceptors)
+        .with_requests(requests)
+        .with_msg_delay_rng(rng)
+        .with_faults(faults)
+        .with_monitor()
+        .build()
+        .unwrap();
+
+    simulator.run().unwrap();
+
+    match simulator.ensure_safety() {
+        Ok(()) => (),
+        Err(e) => {
+            for l in simulator.log.iter() {
+                println!("{}", l);
+            }
+            return TestResult::error(e);
+        }
+    }
+
+    println!(
+        "classic: {:?}, nack: {:?}",
+        classic_stats,
+        simulator.stats()
+    );
+
+    TestResult::passed()
 }